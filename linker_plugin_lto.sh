@@ -0,0 +1,63 @@
+#!/bin/sh
+
+# Linker to use with -Clinker-plugin-lto:
+#
+#     -Clinker-plugin-lto -Clinker=path/to/linker_plugin_lto.sh
+#
+# rustc passes the options of the LLVM gold plugin to the linker (-plugin-opt=O2 and
+# -plugin-opt=mcpu=<cpu>), but the GCC LTO plugin takes its options as arguments of lto-wrapper,
+# so it fails to open a file named "O2". Convert these options to the equivalent gcc options and
+# call gcc (or the driver in the CG_GCCJIT_LINKER env var).
+
+cc=${CG_GCCJIT_LINKER:-gcc}
+
+# NOTE: the target CPU is selected with -march on x86 and with -mcpu on the other architectures.
+case "$("$cc" -dumpmachine)" in
+    x86_64*|i?86*)
+        cpu_option=-march
+        ;;
+    *)
+        cpu_option=-mcpu
+        ;;
+esac
+
+for arg do
+    shift
+    case "$arg" in
+        -Wl,*)
+            ;;
+        *)
+            set -- "$@" "$arg"
+            continue
+            ;;
+    esac
+
+    linker_options=
+    old_ifs=$IFS
+    IFS=,
+    set -f
+    for option in ${arg#-Wl,}; do
+        case "$option" in
+            -plugin-opt=O[0-3])
+                set -- "$@" "-${option#-plugin-opt=}"
+                ;;
+            -plugin-opt=mcpu=*)
+                set -- "$@" "$cpu_option=${option#-plugin-opt=mcpu=}"
+                ;;
+            -plugin-opt=sample-profile=*)
+                set -- "$@" "-fauto-profile=${option#-plugin-opt=sample-profile=}"
+                ;;
+            *)
+                linker_options="$linker_options,$option"
+                ;;
+        esac
+    done
+    set +f
+    IFS=$old_ifs
+
+    if [ -n "$linker_options" ]; then
+        set -- "$@" "-Wl$linker_options"
+    fi
+done
+
+exec "$cc" "$@"
@@ -185,7 +185,7 @@ impl<'a, 'gcc, 'tcx> AsmBuilderMethods<'tcx> for Builder<'a, 'gcc, 'tcx> {
                         }
                     };
 
-                    let tmp_var = self.current_func().new_local(None, ty, "output_register");
+                    let tmp_var = self.current_func().new_local(self.location, ty, "output_register");
                     outputs.push(AsmOutOperand {
                         constraint,
                         rust_idx,
@@ -226,7 +226,7 @@ impl<'a, 'gcc, 'tcx> AsmBuilderMethods<'tcx> for Builder<'a, 'gcc, 'tcx> {
                     // values to be of *exactly the same type*, not just "compatible".
                     // I'm not sure if GCC is so picky too, but better safe than sorry.
                     let ty = in_value.layout.gcc_type(self.cx, false);
-                    let tmp_var = self.current_func().new_local(None, ty, "output_register");
+                    let tmp_var = self.current_func().new_local(self.location, ty, "output_register");
 
                     // If the out_place is None (i.e `inout(reg) _` syntax was used), we translate
                     // it to one "readwrite (+) output variable", otherwise we translate it to two
@@ -281,7 +281,7 @@ impl<'a, 'gcc, 'tcx> AsmBuilderMethods<'tcx> for Builder<'a, 'gcc, 'tcx> {
                         };
 
                         let ty = out_place.layout.gcc_type(self.cx, false);
-                        let tmp_var = self.current_func().new_local(None, ty, "output_register");
+                        let tmp_var = self.current_func().new_local(self.location, ty, "output_register");
                         tmp_var.set_register_name(reg_name);
 
                         outputs.push(AsmOutOperand {
@@ -301,9 +301,9 @@ impl<'a, 'gcc, 'tcx> AsmBuilderMethods<'tcx> for Builder<'a, 'gcc, 'tcx> {
                 InlineAsmOperandRef::In { reg, value } => {
                     if let ConstraintOrRegister::Register(reg_name) = reg_to_gcc(reg) {
                         let ty = value.layout.gcc_type(self.cx, false);
-                        let reg_var = self.current_func().new_local(None, ty, "input_register");
+                        let reg_var = self.current_func().new_local(self.location, ty, "input_register");
                         reg_var.set_register_name(reg_name);
-                        self.llbb().add_assignment(None, reg_var, value.immediate());
+                        self.llbb().add_assignment(self.location, reg_var, value.immediate());
 
                         inputs.push(AsmInOperand {
                             constraint: "r".into(),
@@ -320,7 +320,7 @@ impl<'a, 'gcc, 'tcx> AsmBuilderMethods<'tcx> for Builder<'a, 'gcc, 'tcx> {
                     if let ConstraintOrRegister::Register(reg_name) = reg_to_gcc(reg) {
                         // See explanation in the first pass.
                         let ty = in_value.layout.gcc_type(self.cx, false);
-                        let tmp_var = self.current_func().new_local(None, ty, "output_register");
+                        let tmp_var = self.current_func().new_local(self.location, ty, "output_register");
                         tmp_var.set_register_name(reg_name);
 
                         outputs.push(AsmOutOperand {
@@ -443,7 +443,7 @@ impl<'a, 'gcc, 'tcx> AsmBuilderMethods<'tcx> for Builder<'a, 'gcc, 'tcx> {
         // 4. Generate Extended Asm block
 
         let block = self.llbb();
-        let extended_asm = block.add_extended_asm(None, &template_str);
+        let extended_asm = block.add_extended_asm(self.location, &template_str);
 
         for op in &outputs {
             extended_asm.add_output_operand(None, &op.to_constraint(), op.tmp_var);
@@ -0,0 +1,58 @@
+use gccjit::{FnAttribute, Function};
+use rustc_attr::InlineAttr;
+use rustc_middle::middle::codegen_fn_attrs::CodegenFnAttrFlags;
+use rustc_middle::ty;
+
+use crate::context::CodegenCx;
+use crate::to_gcc_feature;
+
+/// Get GCC attribute for the provided inline heuristic.
+#[inline]
+fn inline_attr<'gcc>(inline: InlineAttr) -> Option<FnAttribute<'gcc>> {
+    match inline {
+        InlineAttr::Hint => Some(FnAttribute::Inline),
+        InlineAttr::Always => Some(FnAttribute::AlwaysInline),
+        InlineAttr::Never => Some(FnAttribute::NoInline),
+        InlineAttr::None => None,
+    }
+}
+
+/// Composite function which sets GCC attributes for function depending on its AST (`#[attribute]`)
+/// attributes.
+pub fn from_fn_attrs<'gcc, 'tcx>(cx: &CodegenCx<'gcc, 'tcx>, func: Function<'gcc>, instance: ty::Instance<'tcx>) {
+    let codegen_fn_attrs = cx.tcx.codegen_fn_attrs(instance.def_id());
+
+    let inline =
+        if codegen_fn_attrs.inline == InlineAttr::None && instance.def.requires_inline(cx.tcx) {
+            InlineAttr::Hint
+        }
+        else {
+            codegen_fn_attrs.inline
+        };
+    if let Some(attribute) = inline_attr(inline) {
+        if let FnAttribute::AlwaysInline = attribute {
+            // NOTE: gcc warns that the always_inline functions might not be inlinable if they
+            // are not also declared inline.
+            func.add_attribute(FnAttribute::Inline);
+        }
+        func.add_attribute(attribute);
+    }
+
+    if codegen_fn_attrs.flags.contains(CodegenFnAttrFlags::COLD) {
+        func.add_attribute(FnAttribute::Cold);
+    }
+
+    if let Some(link_section) = codegen_fn_attrs.link_section {
+        func.add_attribute(FnAttribute::Section(link_section.as_str()));
+    }
+
+    // NOTE: the features of the target (-Ctarget-cpu and -Ctarget-feature) are already enabled
+    // for the whole context, so only the features enabled by #[target_feature] are needed here.
+    let target_features = codegen_fn_attrs.target_features.iter()
+        .filter_map(|feature| to_gcc_feature(cx.tcx.sess, feature.as_str()))
+        .collect::<Vec<_>>()
+        .join(",");
+    if !target_features.is_empty() {
+        func.add_attribute(FnAttribute::Target(&target_features));
+    }
+}
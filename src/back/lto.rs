@@ -0,0 +1,438 @@
+use std::ffi::CString;
+use std::fs::{self, File};
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+
+use gccjit::{Context, OutputKind};
+use rustc_codegen_ssa::{looks_like_rust_object_file, ModuleCodegen, ModuleKind};
+use rustc_codegen_ssa::back::lto::{LtoModuleCodegen, SerializedModule, ThinModule, ThinShared};
+use rustc_codegen_ssa::back::write::{CodegenContext, FatLTOInput, TargetMachineFactoryConfig};
+use rustc_codegen_ssa::traits::{ModuleBufferMethods, ThinBufferMethods};
+use rustc_errors::{ColorConfig, Handler};
+use rustc_middle::dep_graph::WorkProduct;
+use rustc_session::config::{CrateType, Lto};
+use rustc_session::Session;
+use rustc_span::fatal_error::FatalError;
+use tempfile::TempDir;
+
+use crate::{GccCodegenBackend, GccContext, set_optimization_level};
+
+fn crate_type_allows_lto(crate_type: CrateType) -> bool {
+    match crate_type {
+        CrateType::Executable | CrateType::Staticlib | CrateType::Cdylib => true,
+        CrateType::Dylib | CrateType::Rlib | CrateType::ProcMacro => false,
+    }
+}
+
+/// Get the object files of the upstream crates that need to be part of the LTO.
+///
+/// NOTE: when doing LTO for the entire crate graph, rustc doesn't link the object files of the
+/// upstream rlibs, so they must be combined with the LTO module. They contain GIMPLE bytecode
+/// when the upstream crates were compiled with -Cembed-bitcode=yes (the default): otherwise, the
+/// GCC LTO driver combines their machine code without optimizing it.
+fn prepare_lto(cgcx: &CodegenContext<GccCodegenBackend>, diag_handler: &Handler) -> Result<Vec<(SerializedModule<ModuleBuffer>, String)>, FatalError> {
+    let mut upstream_modules = Vec::new();
+    // We're just doing LTO for our one crate.
+    if cgcx.lto == Lto::ThinLocal {
+        return Ok(upstream_modules);
+    }
+
+    if cgcx.opts.cg.prefer_dynamic {
+        diag_handler.struct_err("cannot prefer dynamic linking when performing LTO")
+            .note("only 'staticlib', 'bin', and 'cdylib' outputs are supported with LTO")
+            .emit();
+        return Err(FatalError);
+    }
+
+    // Make sure we actually can run LTO.
+    for crate_type in cgcx.crate_types.iter() {
+        if !crate_type_allows_lto(*crate_type) {
+            let error = diag_handler.fatal("lto can only be run for executables, cdylibs and static library outputs");
+            return Err(error);
+        }
+    }
+
+    for &(_cnum, ref path) in cgcx.each_linked_rlib_for_lto.iter() {
+        let _timer = cgcx.prof.generic_activity_with_arg("GCC_lto_load_upstream_objects", &*path.to_string_lossy());
+        let file =
+            match File::open(path) {
+                Ok(file) => file,
+                Err(error) => {
+                    let msg = format!("failed to open rlib {} for LTO: {}", path.display(), error);
+                    return Err(diag_handler.fatal(&msg));
+                },
+            };
+        let mut archive = ar::Archive::new(file);
+        while let Some(entry) = archive.next_entry() {
+            let mut entry =
+                match entry {
+                    Ok(entry) => entry,
+                    Err(error) => {
+                        let msg = format!("failed to read rlib {} for LTO: {}", path.display(), error);
+                        return Err(diag_handler.fatal(&msg));
+                    },
+                };
+            let name = String::from_utf8_lossy(entry.header().identifier()).into_owned();
+            if !looks_like_rust_object_file(&name) {
+                continue;
+            }
+            let mut data = vec![];
+            if let Err(error) = entry.read_to_end(&mut data) {
+                let msg = format!("failed to read {} from rlib {} for LTO: {}", name, path.display(), error);
+                return Err(diag_handler.fatal(&msg));
+            }
+            upstream_modules.push((SerializedModule::FromRlib(data), name));
+        }
+    }
+
+    Ok(upstream_modules)
+}
+
+/// Create the context of a module created by the LTO, with the options of the target.
+fn new_lto_context(cgcx: &CodegenContext<GccCodegenBackend>, module_name: &str) -> Context<'static> {
+    let context = Context::default();
+    let config = TargetMachineFactoryConfig::new(cgcx, module_name);
+    let target_machine = (cgcx.tm_factory)(config).expect("target machine");
+    for option in &target_machine.target_options {
+        context.add_command_line_option(option);
+    }
+    context
+}
+
+/// Serialized form of a module for LTO: the content of an object file containing the GIMPLE
+/// bytecode of the module.
+pub struct ModuleBuffer(Vec<u8>);
+
+impl ModuleBuffer {
+    pub fn new(data: Vec<u8>) -> ModuleBuffer {
+        ModuleBuffer(data)
+    }
+}
+
+impl ModuleBufferMethods for ModuleBuffer {
+    fn data(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+pub(crate) fn serialize_module(module: ModuleCodegen<GccContext>) -> (String, ModuleBuffer) {
+    let data = compile_to_gimple_bytecode(&module)
+        .unwrap_or_else(|msg| gimple_bytecode_error(&msg));
+    (module.name, ModuleBuffer::new(data))
+}
+
+pub(crate) fn run_fat(cgcx: &CodegenContext<GccCodegenBackend>, diag_handler: &Handler, modules: Vec<FatLTOInput<GccCodegenBackend>>, cached_modules: Vec<(SerializedModule<ModuleBuffer>, WorkProduct)>) -> Result<LtoModuleCodegen<GccCodegenBackend>, FatalError> {
+    let tmp_path =
+        match TempDir::new() {
+            Ok(tmp_path) => tmp_path,
+            Err(error) => {
+                let msg = format!("failed to create temporary directory for LTO: {}", error);
+                return Err(diag_handler.fatal(&msg));
+            },
+        };
+
+    let upstream_modules = prepare_lto(cgcx, diag_handler)?;
+
+    // Sort out all our lists of incoming modules into two lists.
+    let mut in_memory = Vec::new();
+    let mut serialized_modules: Vec<_> = cached_modules.into_iter()
+        .map(|(buffer, work_product)| (buffer, work_product.cgu_name))
+        .collect();
+    for module in modules {
+        match module {
+            FatLTOInput::InMemory(module) => in_memory.push(module),
+            FatLTOInput::Serialized { name, buffer } => {
+                serialized_modules.push((SerializedModule::Local(buffer), name));
+            },
+        }
+    }
+
+    // NOTE: we cannot merge GCC contexts in memory like cg_llvm is doing. Instead, we take the
+    // first regular module as a base module and all other modules are compiled to object files
+    // containing GIMPLE bytecode that will be combined with the base module by the GCC LTO
+    // driver when the base module is compiled.
+    let base_index = in_memory.iter()
+        .position(|module| module.kind == ModuleKind::Regular);
+    let mut module: ModuleCodegen<GccContext> =
+        match base_index {
+            Some(base_index) => in_memory.remove(base_index),
+            None => {
+                // NOTE: all the regular modules are serialized when rustc saves them before LTO
+                // (e.g. with -Cincremental or -Csave-temps), so use an empty module as the base
+                // module and combine all the serialized modules with it.
+                let name = serialized_modules.iter()
+                    .map(|(_, name)| name)
+                    .min()
+                    .unwrap_or_else(|| diag_handler.fatal("no module to use as a base for fat LTO").raise())
+                    .clone();
+                let context = new_lto_context(cgcx, &name);
+                set_optimization_level(cgcx, &name, &context, cgcx.config(ModuleKind::Regular));
+                let module_llvm = GccContext {
+                    context,
+                    should_combine_object_files: false,
+                    temp_dir: None,
+                    combined_outputs: vec![],
+                    keep_in_memory_for_thin_lto: false,
+                };
+                ModuleCodegen { name, module_llvm, kind: ModuleKind::Regular }
+            },
+        };
+
+    for in_memory_module in in_memory {
+        let _timer = cgcx.prof.generic_activity_with_arg("GCC_fat_lto_compile_module", &*in_memory_module.name);
+        let config = cgcx.config(in_memory_module.kind);
+        // NOTE: the optimization level needs to be set here in order for LTO to do its job.
+        set_optimization_level(cgcx, &in_memory_module.name, &in_memory_module.module_llvm.context, config);
+        let data = compile_to_gimple_bytecode(&in_memory_module)
+            .map_err(|msg| diag_handler.fatal(&msg))?;
+        serialized_modules.push((SerializedModule::Local(ModuleBuffer::new(data)), in_memory_module.name));
+    }
+
+    // Sort the modules to ensure we produce deterministic results.
+    serialized_modules.sort_by(|(_, name1), (_, name2)| name1.cmp(name2));
+    // NOTE: the upstream modules are already in a deterministic order.
+    serialized_modules.extend(upstream_modules);
+
+    let mut serialized_bitcode = Vec::with_capacity(serialized_modules.len());
+    for (serialized_module, name) in serialized_modules {
+        let _timer = cgcx.prof.generic_activity_with_arg("GCC_fat_lto_link_module", &*name);
+        let path = tmp_path.path().join(format!("{}.o", name));
+        if let Err(error) = fs::write(&path, serialized_module.data()) {
+            let msg = format!("failed to write LTO module {}: {}", name, error);
+            return Err(diag_handler.fatal(&msg));
+        }
+        module.module_llvm.context.add_driver_option(path.to_str().expect("path to str"));
+        module.module_llvm.should_combine_object_files = true;
+        serialized_bitcode.push(serialized_module);
+    }
+    module.module_llvm.context.add_command_line_option("-flto");
+    module.module_llvm.context.add_driver_option("-flto");
+
+    // NOTE: keep the temporary directory alive until the base module is compiled since it
+    // contains the object files to combine.
+    module.module_llvm.temp_dir = Some(tmp_path);
+
+    Ok(LtoModuleCodegen::Fat { module: Some(module), _serialized_bitcode: serialized_bitcode })
+}
+
+/// Whether the modules are kept in memory by thin local LTO instead of being serialized to GIMPLE
+/// bytecode.
+///
+/// NOTE: thin local LTO is the default at opt-level > 0 with multiple codegen units, but gcc has no
+/// cross-module optimization to do without the whole program, so the codegen units are compiled as
+/// is to their own object files, like without LTO. rustc writes the serialized modules to the
+/// incremental cache (and with -Csave-temps), so they are only kept in memory when it doesn't.
+pub(crate) fn keep_in_memory_for_thin_lto(sess: &Session) -> bool {
+    sess.lto() == Lto::ThinLocal && sess.opts.incremental.is_none() && !sess.opts.cg.save_temps
+}
+
+/// Serialized form of a module for thin LTO: the content of an object file containing the GIMPLE
+/// bytecode of the module, or the module itself when it is kept in memory by thin local LTO.
+pub struct ThinBuffer {
+    data: Vec<u8>,
+    module: Mutex<Option<ModuleCodegen<GccContext>>>,
+}
+
+impl ThinBufferMethods for ThinBuffer {
+    // NOTE: this is empty for the modules kept in memory, for which rustc only uses the size to
+    // estimate the cost of the module.
+    fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+pub(crate) fn prepare_thin(module: ModuleCodegen<GccContext>) -> (String, ThinBuffer) {
+    if module.module_llvm.keep_in_memory_for_thin_lto {
+        let name = module.name.clone();
+        return (name, ThinBuffer { data: vec![], module: Mutex::new(Some(module)) });
+    }
+
+    let data = compile_to_gimple_bytecode(&module)
+        .unwrap_or_else(|msg| gimple_bytecode_error(&msg));
+    (module.name, ThinBuffer { data, module: Mutex::new(None) })
+}
+
+/// Compile the module to an object file containing only GIMPLE bytecode and return its content.
+fn compile_to_gimple_bytecode(module: &ModuleCodegen<GccContext>) -> Result<Vec<u8>, String> {
+    let tmp_path = TempDir::new()
+        .map_err(|error| format!("failed to create temporary directory for the GIMPLE bytecode of module {}: {}", module.name, error))?;
+    let path = tmp_path.path().join(format!("{}.o", module.name));
+    let context = &module.module_llvm.context;
+    // NOTE: the optimization level was already set by optimize().
+    context.add_command_line_option("-flto");
+    context.compile_to_file(OutputKind::ObjectFile, path.to_str().expect("path to str"));
+    fs::read(&path)
+        .map_err(|error| format!("failed to read GIMPLE bytecode of module {}: {}", module.name, error))
+}
+
+/// Report an error of compile_to_gimple_bytecode() and abort the compilation.
+///
+/// NOTE: prepare_thin() and serialize_module() have no diagnostic handler and cannot return an
+/// error, so emit the error with a new handler and abort the codegen worker like
+/// `Handler::fatal().raise()` does for the other errors.
+fn gimple_bytecode_error(msg: &str) -> ! {
+    let diag_handler = Handler::with_tty_emitter(ColorConfig::Auto, true, None, None);
+    diag_handler.fatal(msg).raise()
+}
+
+/// Data shared by the thin LTO modules.
+pub struct ThinData {
+    /// Index of the module in which the whole program is compiled.
+    whole_program_index: usize,
+    /// Number of modules of the crate: the next modules come from the upstream crates.
+    local_module_count: usize,
+}
+
+/// gcc has no equivalent to the summary-based function importing of ThinLTO. Instead, thin LTO is
+/// mapped onto the WHOPR mode of gcc: all the modules are combined into a single LTO module and
+/// the GCC LTO driver partitions the whole program and optimizes the partitions in parallel.
+///
+/// NOTE: rustc expects one LTO module per codegen unit, so the whole program is compiled in the
+/// module of the first codegen unit and the others are empty. This way, every codegen unit has a
+/// work product and the objects can be reused when no codegen unit changed.
+///
+/// Thin local LTO keeps one object file per codegen unit instead (see
+/// keep_in_memory_for_thin_lto()): each module is compiled on its own.
+pub(crate) fn run_thin(cgcx: &CodegenContext<GccCodegenBackend>, diag_handler: &Handler, modules: Vec<(String, ThinBuffer)>, cached_modules: Vec<(SerializedModule<ModuleBuffer>, WorkProduct)>) -> Result<(Vec<LtoModuleCodegen<GccCodegenBackend>>, Vec<WorkProduct>), FatalError> {
+    let upstream_modules = prepare_lto(cgcx, diag_handler)?;
+
+    // NOTE: the objects of the previous compilation are only valid if none of the modules they
+    // were compiled from changed, including the upstream modules, which are not tracked by the
+    // incremental compilation.
+    if modules.is_empty() && upstream_modules.is_empty() {
+        let work_products = cached_modules.into_iter()
+            .map(|(_, work_product)| work_product)
+            .collect();
+        return Ok((vec![], work_products));
+    }
+
+    let mut modules = modules;
+    // Sort the modules to ensure we produce deterministic results.
+    modules.sort_by(|(name1, _), (name2, _)| name1.cmp(name2));
+
+    let mut module_names = Vec::with_capacity(modules.len() + cached_modules.len() + upstream_modules.len());
+    let mut thin_buffers = Vec::with_capacity(modules.len());
+    for (name, buffer) in modules {
+        module_names.push(CString::new(name).expect("module name"));
+        thin_buffers.push(buffer);
+    }
+
+    let mut serialized_modules = Vec::with_capacity(cached_modules.len() + upstream_modules.len());
+    for (module, work_product) in cached_modules {
+        module_names.push(CString::new(work_product.cgu_name).expect("module name"));
+        serialized_modules.push(module);
+    }
+
+    let local_module_count = module_names.len();
+    if local_module_count == 0 {
+        return Ok((vec![], vec![]));
+    }
+
+    for (module, name) in upstream_modules {
+        module_names.push(CString::new(name).expect("module name"));
+        serialized_modules.push(module);
+    }
+
+    // NOTE: use the module with the smallest name for the whole program, so that it is the same
+    // module whether the modules were cached or not.
+    let whole_program_index = module_names[..local_module_count].iter()
+        .enumerate()
+        .min_by(|(_, name1), (_, name2)| name1.cmp(name2))
+        .map(|(index, _)| index)
+        .expect("module");
+
+    let shared = Arc::new(ThinShared {
+        data: ThinData {
+            whole_program_index,
+            local_module_count,
+        },
+        thin_buffers,
+        serialized_modules,
+        module_names,
+    });
+    let modules = (0..local_module_count)
+        .map(|idx| LtoModuleCodegen::Thin(ThinModule { shared: shared.clone(), idx }))
+        .collect();
+    Ok((modules, vec![]))
+}
+
+pub(crate) fn optimize_thin(cgcx: &CodegenContext<GccCodegenBackend>, thin: &ThinModule<GccCodegenBackend>) -> Result<ModuleCodegen<GccContext>, FatalError> {
+    let shared = &thin.shared;
+    let in_memory_module = shared.thin_buffers.get(thin.idx)
+        .and_then(|thin_buffer| thin_buffer.module.lock().expect("thin LTO module").take());
+    if let Some(module) = in_memory_module {
+        return Ok(module);
+    }
+
+    let context = new_lto_context(cgcx, thin.name());
+    let config = cgcx.config(ModuleKind::Regular);
+    set_optimization_level(cgcx, thin.name(), &context, config);
+
+    let whole_program = cgcx.lto != Lto::ThinLocal;
+    if whole_program && thin.idx != shared.data.whole_program_index {
+        let module_llvm = GccContext {
+            context,
+            should_combine_object_files: false,
+            temp_dir: None,
+            combined_outputs: vec![],
+            keep_in_memory_for_thin_lto: false,
+        };
+        return Ok(ModuleCodegen { name: thin.name().to_string(), module_llvm, kind: ModuleKind::Regular });
+    }
+
+    let diag_handler = cgcx.create_diag_handler();
+    let tmp_path =
+        match TempDir::new() {
+            Ok(tmp_path) => tmp_path,
+            Err(error) => {
+                let msg = format!("failed to create temporary directory for LTO: {}", error);
+                return Err(diag_handler.fatal(&msg));
+            },
+        };
+
+    // NOTE: with thin local LTO, the GIMPLE bytecode of the module was only serialized for the
+    // incremental cache, so compile only this module.
+    let module_indices =
+        if whole_program {
+            0..shared.module_names.len()
+        }
+        else {
+            thin.idx..thin.idx + 1
+        };
+    let thin_buffer_count = shared.thin_buffers.len();
+    for index in module_indices {
+        let name = shared.module_names[index].to_str().expect("module name");
+        let _timer = cgcx.prof.generic_activity_with_arg("GCC_thin_lto_link_module", name);
+        let data =
+            match shared.thin_buffers.get(index) {
+                Some(thin_buffer) => thin_buffer.data(),
+                None => shared.serialized_modules[index - thin_buffer_count].data(),
+            };
+        let path = tmp_path.path().join(format!("{}.o", name));
+        if let Err(error) = fs::write(&path, data) {
+            let msg = format!("failed to write LTO module {}: {}", name, error);
+            return Err(diag_handler.fatal(&msg));
+        }
+        context.add_driver_option(path.to_str().expect("path to str"));
+    }
+
+    context.add_command_line_option("-flto");
+    if whole_program {
+        // NOTE: run the link-time optimizations of the partitions in as many parallel jobs as
+        // there were codegen units.
+        context.add_driver_option(&format!("-flto={}", shared.data.local_module_count));
+        context.add_driver_option("-flto-partition=balanced");
+    }
+    else {
+        context.add_driver_option("-flto");
+    }
+
+    let module_llvm = GccContext {
+        context,
+        should_combine_object_files: true,
+        temp_dir: Some(tmp_path),
+        combined_outputs: vec![],
+        keep_in_memory_for_thin_lto: false,
+    };
+    Ok(ModuleCodegen { name: thin.name().to_string(), module_llvm, kind: ModuleKind::Regular })
+}
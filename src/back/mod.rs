@@ -1 +1,2 @@
+pub mod lto;
 pub mod write;
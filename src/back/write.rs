@@ -1,16 +1,21 @@
-use std::{env, fs};
+use std::{env, fs, io};
+use std::ffi::OsStr;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
 use gccjit::OutputKind;
-use rustc_codegen_ssa::{CompiledModule, ModuleCodegen};
+use rustc_codegen_ssa::{CompiledModule, ModuleCodegen, ModuleKind};
 use rustc_codegen_ssa::back::write::{CodegenContext, EmitObj, ModuleConfig};
 use rustc_errors::Handler;
-use rustc_session::config::OutputType;
+use rustc_session::config::{OutputType, SplitDwarfKind};
 use rustc_span::fatal_error::FatalError;
 use rustc_target::spec::SplitDebuginfo;
+use tempfile::TempDir;
 
 use crate::{GccCodegenBackend, GccContext};
 
-pub(crate) unsafe fn codegen(cgcx: &CodegenContext<GccCodegenBackend>, _diag_handler: &Handler, module: ModuleCodegen<GccContext>, config: &ModuleConfig) -> Result<CompiledModule, FatalError> {
+pub(crate) unsafe fn codegen(cgcx: &CodegenContext<GccCodegenBackend>, diag_handler: &Handler, module: ModuleCodegen<GccContext>, config: &ModuleConfig) -> Result<CompiledModule, FatalError> {
     let _timer = cgcx.prof.generic_activity_with_arg("LLVM_module_codegen", &module.name[..]);
     {
         let context = &module.module_llvm.context;
@@ -18,15 +23,18 @@ pub(crate) unsafe fn codegen(cgcx: &CodegenContext<GccCodegenBackend>, _diag_han
         let module_name = module.name.clone();
         let module_name = Some(&module_name[..]);
 
-        let _bc_out = cgcx.output_filenames.temp_path(OutputType::Bitcode, module_name);
+        let bc_out = cgcx.output_filenames.temp_path(OutputType::Bitcode, module_name);
         let obj_out = cgcx.output_filenames.temp_path(OutputType::Object, module_name);
 
-        if config.bitcode_needed() {
-            // TODO(antoyo)
-        }
-
         if config.emit_ir {
-            unimplemented!();
+            let _timer = cgcx
+                .prof
+                .generic_activity_with_arg("LLVM_module_codegen_emit_ir", &*module.name);
+            let path = cgcx.output_filenames.temp_path(OutputType::LlvmAssembly, module_name);
+            // NOTE: don't update the locations to point to the dump since this would change the
+            // debug info of the generated code.
+            context.dump_to_file(path.to_str().expect("path to str"), false);
+            append_combined_outputs(diag_handler, &module.module_llvm, OutputType::LlvmAssembly, &path)?;
         }
 
         if config.emit_asm {
@@ -37,6 +45,28 @@ pub(crate) unsafe fn codegen(cgcx: &CodegenContext<GccCodegenBackend>, _diag_han
             context.compile_to_file(OutputKind::Assembler, path.to_str().expect("path to str"));
         }
 
+        if config.bitcode_needed() {
+            // NOTE: the equivalent of the LLVM bitcode is the GIMPLE bytecode that gcc emits in the
+            // LTO sections of the object files. Since the options cannot be removed from the
+            // context, this is done after emitting the assembly so that it doesn't contain the LTO
+            // sections.
+            context.add_command_line_option("-flto");
+            if config.emit_obj != EmitObj::Bitcode {
+                // NOTE: also emit the machine code so that the object files can be linked without
+                // LTO. This makes the object files bigger since they contain both the GIMPLE
+                // bytecode and the machine code. This happens for every rlib compiled with the
+                // default -Cembed-bitcode=yes, but cargo disables it when LTO is not used.
+                context.add_command_line_option("-ffat-lto-objects");
+            }
+
+            if config.emit_bc {
+                let _timer = cgcx
+                    .prof
+                    .generic_activity_with_arg("LLVM_module_codegen_emit_bitcode", &*module.name);
+                context.compile_to_file(OutputKind::ObjectFile, bc_out.to_str().expect("path to str"));
+            }
+        }
+
         match config.emit_obj {
             EmitObj::ObjectCode(_) => {
                 let _timer = cgcx
@@ -59,25 +89,225 @@ pub(crate) unsafe fn codegen(cgcx: &CodegenContext<GccCodegenBackend>, _diag_han
                     let path = &format!("/tmp/gccjit_dumps/{}.c", module.name);
                     context.dump_to_file(path, true);
                 }
-                context.compile_to_file(OutputKind::ObjectFile, obj_out.to_str().expect("path to str"));
+
+                let dwo_out = cgcx.output_filenames.temp_path_dwo(module_name);
+                let dwo_out =
+                    match (cgcx.split_debuginfo, cgcx.split_dwarf_kind) {
+                        // Don't change how DWARF is emitted when disabled.
+                        (SplitDebuginfo::Off, _) => None,
+                        // Don't provide a DWARF object path if split debuginfo is enabled but this
+                        // is a platform that doesn't support Split DWARF.
+                        _ if !cgcx.target_can_use_split_dwarf => None,
+                        // Don't provide a DWARF object path in single mode, sections will be
+                        // written into the object as normal but ignored by linker.
+                        (_, SplitDwarfKind::Single) => None,
+                        // Emit (a subset of the) DWARF into a separate dwarf object file in split
+                        // mode.
+                        (_, SplitDwarfKind::Split) => Some(dwo_out.as_path()),
+                    };
+                if let Some(dwo_out) = dwo_out {
+                    context.add_command_line_option("-gsplit-dwarf");
+                    // NOTE: the skeleton unit of the object file references the DWARF object named
+                    // after the dump base, so set it to dwo_out without its extension.
+                    // The dump base of the modules using gcov is already set and changing it would
+                    // change the names of the gcov files (see set_gcov_dump_base()), so their
+                    // DWARF objects can only be found by their id, like when they are packed.
+                    if !uses_gcov_dump_base(cgcx, &module) {
+                        let dump_dir = dwo_out.parent().expect("dwo directory");
+                        let dump_base = dwo_out.file_stem().expect("dwo file stem");
+                        context.add_command_line_option("-dumpdir");
+                        context.add_command_line_option(&format!("{}/", dump_dir.display()));
+                        context.add_command_line_option("-dumpbase");
+                        context.add_command_line_option(dump_base.to_str().expect("path to str"));
+                    }
+                }
+
+                if module.module_llvm.should_combine_object_files {
+                    // NOTE: the object files of the other modules were added as driver options by
+                    // the LTO pass or by link(): do a relocatable link to combine them with this
+                    // module into a single object file.
+                    context.add_driver_option("-Wl,-r");
+                    context.add_driver_option("-nostdlib");
+                    // NOTE: this is needed to not get a LTO object file as output.
+                    context.add_driver_option("-flinker-output=nolto-rel");
+                    context.compile_to_file(OutputKind::Executable, obj_out.to_str().expect("path to str"));
+                }
+                else {
+                    context.compile_to_file(OutputKind::ObjectFile, obj_out.to_str().expect("path to str"));
+                }
+
+                if let Some(dwo_out) = dwo_out {
+                    split_dwarf_object(cgcx, diag_handler, &obj_out, dwo_out)?;
+                }
             }
 
             EmitObj::Bitcode => {
-                // TODO(antoyo)
+                let _timer = cgcx
+                    .prof
+                    .generic_activity_with_arg("LLVM_module_codegen_emit_obj", &*module.name);
+                // NOTE: this is used by -Clinker-plugin-lto: the object file contains only the
+                // GIMPLE bytecode (-flto was added above) so that the optimizations and the code
+                // generation are done by the GCC LTO linker plugin at link time, possibly together
+                // with C code compiled with -flto.
+                if module.module_llvm.should_combine_object_files {
+                    context.add_driver_option("-Wl,-r");
+                    context.add_driver_option("-nostdlib");
+                    // NOTE: this is needed to get a LTO object file as output.
+                    context.add_driver_option("-flinker-output=rel");
+                    context.compile_to_file(OutputKind::Executable, obj_out.to_str().expect("path to str"));
+                }
+                else {
+                    context.compile_to_file(OutputKind::ObjectFile, obj_out.to_str().expect("path to str"));
+                }
             }
 
             EmitObj::None => {}
         }
+
+        if let Ok(Some(error)) = context.get_first_error() {
+            let msg = format!("failed to compile module {}: {}", module.name, error);
+            return Err(diag_handler.fatal(&msg));
+        }
     }
 
     Ok(module.into_compiled_module(
         config.emit_obj != EmitObj::None,
-        cgcx.target_can_use_split_dwarf && cgcx.split_debuginfo == SplitDebuginfo::Unpacked,
+        cgcx.target_can_use_split_dwarf
+            && cgcx.split_debuginfo != SplitDebuginfo::Off
+            && cgcx.split_dwarf_kind == SplitDwarfKind::Split,
         config.emit_bc,
         &cgcx.output_filenames,
     ))
 }
 
-pub(crate) fn link(_cgcx: &CodegenContext<GccCodegenBackend>, _diag_handler: &Handler, mut _modules: Vec<ModuleCodegen<GccContext>>) -> Result<ModuleCodegen<GccContext>, FatalError> {
-    unimplemented!();
+/// Whether the dump base of the module was set to name the gcov files (see set_gcov_dump_base()).
+fn uses_gcov_dump_base(cgcx: &CodegenContext<GccCodegenBackend>, module: &ModuleCodegen<GccContext>) -> bool {
+    module.kind == ModuleKind::Regular
+        && (cgcx.opts.instrument_coverage() || cgcx.opts.cg.profile_generate.enabled() || cgcx.opts.cg.profile_use.is_some())
+}
+
+/// Whether the object file contains a section named `name`.
+///
+/// NOTE: the names of the sections of an ELF file are null-terminated strings in the section
+/// header string table. Since the names can share their suffixes (e.g. .rela.debug_info and
+/// .debug_info), only the null character after the name is checked.
+fn has_section(object: &[u8], name: &str) -> bool {
+    let name = format!("{}\0", name);
+    object.windows(name.len()).any(|bytes| bytes == name.as_bytes())
+}
+
+/// Get the objcopy of the binutils used by the linker, e.g. aarch64-linux-gnu-objcopy for
+/// aarch64-linux-gnu-gcc.
+fn objcopy(cgcx: &CodegenContext<GccCodegenBackend>) -> PathBuf {
+    let linker = cgcx.opts.cg.linker.as_ref()
+        .and_then(|linker| Some((linker.parent()?, linker.file_name()?.to_str()?)));
+    match linker {
+        Some((dir, linker)) if linker.ends_with("gcc") => {
+            let prefix = &linker[..linker.len() - "gcc".len()];
+            dir.join(format!("{}objcopy", prefix))
+        },
+        _ => PathBuf::from("objcopy"),
+    }
+}
+
+/// Move the DWARF sections of the split debug info from the object file to the DWARF object file.
+///
+/// NOTE: this is usually done by the gcc driver when compiling with -gsplit-dwarf (with objcopy as
+/// well), but libgccjit compiles the object file in a temporary directory, so the .dwo file it
+/// would create is lost. This is why -gsplit-dwarf is not passed to the driver.
+fn split_dwarf_object(cgcx: &CodegenContext<GccCodegenBackend>, diag_handler: &Handler, obj_out: &Path, dwo_out: &Path) -> Result<(), FatalError> {
+    let object =
+        match fs::read(obj_out) {
+            Ok(object) => object,
+            Err(error) => {
+                let msg = format!("failed to read the object file {} to split its DWARF: {}", obj_out.display(), error);
+                return Err(diag_handler.fatal(&msg));
+            },
+        };
+    if has_section(&object, ".debug_info") && !has_section(&object, ".debug_info.dwo") {
+        let msg = format!("the object file {} has no split DWARF sections to move to {}", obj_out.display(), dwo_out.display());
+        return Err(diag_handler.fatal(&msg));
+    }
+
+    let objcopy = objcopy(cgcx);
+    let commands: [&[&OsStr]; 2] = [
+        &["--extract-dwo".as_ref(), obj_out.as_os_str(), dwo_out.as_os_str()],
+        &["--strip-dwo".as_ref(), obj_out.as_os_str()],
+    ];
+    for args in commands {
+        match Command::new(&objcopy).args(args).status() {
+            Ok(status) if status.success() => (),
+            Ok(status) => {
+                let msg = format!("{} failed to split the DWARF object {}: {}", objcopy.display(), dwo_out.display(), status);
+                return Err(diag_handler.fatal(&msg));
+            },
+            Err(error) => {
+                let msg = format!("failed to run {} to split the DWARF object {}: {}", objcopy.display(), dwo_out.display(), error);
+                diag_handler.struct_err(&msg)
+                    .note("objcopy from GNU binutils is required by -Csplit-debuginfo, like by the -gsplit-dwarf option of gcc")
+                    .emit();
+                return Err(FatalError);
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Append the outputs of type `output_type` of the modules combined with `module` by
+/// -Zcombine-cgu to the output of `module` at `path`.
+///
+/// NOTE: the outputs are concatenated, so the combined assembly might not be assembled as is,
+/// since the local labels of the modules can have the same names.
+fn append_combined_outputs(diag_handler: &Handler, module: &GccContext, output_type: OutputType, path: &Path) -> Result<(), FatalError> {
+    for (_, combined_path) in module.combined_outputs.iter().filter(|(typ, _)| *typ == output_type) {
+        let result = File::open(combined_path).and_then(|mut combined_output| {
+            let mut output = OpenOptions::new().append(true).open(path)?;
+            io::copy(&mut combined_output, &mut output)
+        });
+        if let Err(error) = result {
+            let msg = format!("failed to append {} to {}: {}", combined_path.display(), path.display(), error);
+            return Err(diag_handler.fatal(&msg));
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn link(cgcx: &CodegenContext<GccCodegenBackend>, diag_handler: &Handler, mut modules: Vec<ModuleCodegen<GccContext>>) -> Result<ModuleCodegen<GccContext>, FatalError> {
+    let tmp_path =
+        match TempDir::new() {
+            Ok(tmp_path) => tmp_path,
+            Err(error) => {
+                let msg = format!("failed to create temporary directory to link modules: {}", error);
+                return Err(diag_handler.fatal(&msg));
+            },
+        };
+
+    // Sort the modules to ensure we produce deterministic results.
+    modules.sort_by(|module1, module2| module1.name.cmp(&module2.name));
+
+    // NOTE: we cannot merge GCC contexts in memory like cg_llvm is doing. Instead, all the other
+    // modules are compiled to object files that will be combined with the first module by a
+    // relocatable link when it is compiled. Their IR is appended to the one of the first module.
+    // Since there's no assembly for the combined module, --emit=asm is rejected in init().
+    let mut module = modules.remove(0);
+    let config = cgcx.config(ModuleKind::Regular);
+    for other_module in modules {
+        let _timer = cgcx.prof.generic_activity_with_arg("GCC_link_module", &*other_module.name);
+        let context = &other_module.module_llvm.context;
+        if config.emit_ir {
+            let path = tmp_path.path().join(format!("{}.gimple", other_module.name));
+            context.dump_to_file(path.to_str().expect("path to str"), false);
+            module.module_llvm.combined_outputs.push((OutputType::LlvmAssembly, path));
+        }
+        if config.emit_obj == EmitObj::Bitcode {
+            context.add_command_line_option("-flto");
+        }
+        let path = tmp_path.path().join(format!("{}.o", other_module.name));
+        context.compile_to_file(OutputKind::ObjectFile, path.to_str().expect("path to str"));
+        module.module_llvm.context.add_driver_option(path.to_str().expect("path to str"));
+    }
+    module.module_llvm.should_combine_object_files = true;
+    module.module_llvm.temp_dir = Some(tmp_path);
+
+    Ok(module)
 }
@@ -1,4 +1,5 @@
 use std::env;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
 use gccjit::{
@@ -6,17 +7,19 @@ use gccjit::{
     FunctionType,
     GlobalKind,
 };
+use rustc_middle::bug;
 use rustc_middle::dep_graph;
 use rustc_middle::ty::TyCtxt;
-use rustc_middle::mir::mono::Linkage;
+use rustc_middle::mir::mono::{Linkage, Visibility};
 use rustc_codegen_ssa::{ModuleCodegen, ModuleKind};
 use rustc_codegen_ssa::base::maybe_create_entry_wrapper;
 use rustc_codegen_ssa::mono_item::MonoItemExt;
-use rustc_codegen_ssa::traits::DebugInfoMethods;
-use rustc_session::config::DebugInfo;
+use rustc_codegen_ssa::traits::{CoverageInfoMethods, DebugInfoMethods, MiscMethods};
+use rustc_session::config::{DebugInfo, SwitchWithOptPath};
 use rustc_span::Symbol;
 
-use crate::GccContext;
+use crate::{GccContext, add_target_options};
+use crate::back::lto::keep_in_memory_for_thin_lto;
 use crate::builder::Builder;
 use crate::context::CodegenCx;
 
@@ -24,15 +27,16 @@ pub fn global_linkage_to_gcc(linkage: Linkage) -> GlobalKind {
     match linkage {
         Linkage::External => GlobalKind::Imported,
         Linkage::AvailableExternally => GlobalKind::Imported,
-        Linkage::LinkOnceAny => unimplemented!(),
-        Linkage::LinkOnceODR => unimplemented!(),
-        Linkage::WeakAny => unimplemented!(),
-        Linkage::WeakODR => unimplemented!(),
-        Linkage::Appending => unimplemented!(),
+        // NOTE: the weak attribute is added for these linkages (see is_weak_linkage()).
+        Linkage::LinkOnceAny => GlobalKind::Exported,
+        Linkage::LinkOnceODR => GlobalKind::Exported,
+        Linkage::WeakAny => GlobalKind::Exported,
+        Linkage::WeakODR => GlobalKind::Exported,
+        Linkage::Appending => bug!("appending linkage is only used for the special LLVM globals"),
         Linkage::Internal => GlobalKind::Internal,
         Linkage::Private => GlobalKind::Internal,
-        Linkage::ExternalWeak => GlobalKind::Imported, // TODO(antoyo): should be weak linkage.
-        Linkage::Common => unimplemented!(),
+        Linkage::ExternalWeak => GlobalKind::Imported,
+        Linkage::Common => GlobalKind::Exported,
     }
 }
 
@@ -40,15 +44,41 @@ pub fn linkage_to_gcc(linkage: Linkage) -> FunctionType {
     match linkage {
         Linkage::External => FunctionType::Exported,
         Linkage::AvailableExternally => FunctionType::Extern,
-        Linkage::LinkOnceAny => unimplemented!(),
-        Linkage::LinkOnceODR => unimplemented!(),
-        Linkage::WeakAny => FunctionType::Exported, // FIXME(antoyo): should be similar to linkonce.
-        Linkage::WeakODR => unimplemented!(),
-        Linkage::Appending => unimplemented!(),
+        // NOTE: the weak attribute is added for these linkages (see is_weak_linkage()).
+        Linkage::LinkOnceAny => FunctionType::Exported,
+        Linkage::LinkOnceODR => FunctionType::Exported,
+        Linkage::WeakAny => FunctionType::Exported,
+        Linkage::WeakODR => FunctionType::Exported,
+        Linkage::Appending => bug!("appending linkage is not valid for functions"),
         Linkage::Internal => FunctionType::Internal,
         Linkage::Private => FunctionType::Internal,
-        Linkage::ExternalWeak => unimplemented!(),
-        Linkage::Common => unimplemented!(),
+        Linkage::ExternalWeak => FunctionType::Extern,
+        Linkage::Common => bug!("common linkage is not valid for functions"),
+    }
+}
+
+pub fn visibility_to_gcc(linkage: Visibility) -> gccjit::Visibility {
+    match linkage {
+        Visibility::Default => gccjit::Visibility::Default,
+        Visibility::Hidden => gccjit::Visibility::Hidden,
+        Visibility::Protected => gccjit::Visibility::Protected,
+    }
+}
+
+/// Whether the symbol needs the weak attribute for this linkage.
+///
+/// NOTE: gccjit doesn't support the linkonce linkages nor comdat groups, so these symbols are
+/// emitted as weak symbols instead: the linker then keeps only one of their definitions instead of
+/// reporting multiple definitions. Unlike linkonce_odr symbols in a comdat group, they are kept
+/// even when they are not used in the object file. The common symbols are also emitted
+/// as weak definitions instead of common symbols. The weak declarations (extern_weak) resolve to
+/// null when there's no definition.
+pub fn is_weak_linkage(linkage: Linkage) -> bool {
+    match linkage {
+        Linkage::LinkOnceAny | Linkage::LinkOnceODR | Linkage::WeakAny | Linkage::WeakODR
+            | Linkage::ExternalWeak | Linkage::Common => true,
+        Linkage::External | Linkage::AvailableExternally | Linkage::Appending | Linkage::Internal
+            | Linkage::Private => false,
     }
 }
 
@@ -81,6 +111,7 @@ pub fn compile_codegen_unit<'tcx>(tcx: TyCtxt<'tcx>, cgu_name: Symbol, supports_
         for arg in &tcx.sess.opts.cg.llvm_args {
             context.add_command_line_option(arg);
         }
+        add_target_options(&context, tcx.sess);
         // NOTE: an optimization (https://github.com/rust-lang/rustc_codegen_gcc/issues/53).
         context.add_command_line_option("-fno-semantic-interposition");
         // NOTE: Rust relies on LLVM not doing TBAA (https://github.com/rust-lang/unsafe-code-guidelines/issues/292).
@@ -91,6 +122,61 @@ pub fn compile_codegen_unit<'tcx>(tcx: TyCtxt<'tcx>, cgu_name: Symbol, supports_
             context.add_command_line_option("-fdata-sections");
         }
 
+        // NOTE: gcc names the coverage notes and the profile data files after the dump base, which
+        // must only be set once, so the profile directory is also used for the coverage files when
+        // both are enabled.
+        let profile_dir =
+            match tcx.sess.opts.cg.profile_generate {
+                SwitchWithOptPath::Enabled(ref profile_dir) => profile_dir.as_deref(),
+                SwitchWithOptPath::Disabled => tcx.sess.opts.cg.profile_use.as_deref(),
+            };
+        let dump_base =
+            if tcx.sess.instrument_coverage() || tcx.sess.opts.cg.profile_generate.enabled() || tcx.sess.opts.cg.profile_use.is_some() {
+                Some(set_gcov_dump_base(&context, tcx, cgu_name, profile_dir))
+            }
+            else {
+                None
+            };
+
+        if tcx.sess.instrument_coverage() {
+            context.add_command_line_option("-fprofile-arcs");
+            context.add_command_line_option("-ftest-coverage");
+            if cgu.is_code_coverage_dead_code_cgu() {
+                // NOTE: the functions generated for the unused functions are internal and never
+                // called, so gcc would remove them when optimizing (see
+                // CodegenCx::define_unused_fn).
+                context.add_command_line_option("-fkeep-static-functions");
+            }
+        }
+
+        if tcx.sess.opts.cg.profile_generate.enabled() {
+            context.add_command_line_option("-fprofile-generate");
+        }
+
+        if let Some(ref profile_dir) = tcx.sess.opts.cg.profile_use {
+            // NOTE: gcc writes one profile data file per object file, so there's no single file
+            // like the .profdata file of LLVM.
+            if !profile_dir.is_dir() {
+                tcx.sess.fatal(&format!(
+                    "`-C profile-use` expects the directory containing the .gcda files written by a build with `-C profile-generate`, but `{}` is not a directory",
+                    profile_dir.display(),
+                ));
+            }
+            let dump_base = dump_base.as_ref().expect("dump base");
+            let profile_data = PathBuf::from(format!("{}.gcda", dump_base.display()));
+            if !profile_data.exists() {
+                tcx.sess.warn(&format!(
+                    "the profile data file `{}` of the codegen unit `{}` does not exist: this codegen unit will be optimized without profile data",
+                    profile_data.display(),
+                    cgu_name,
+                ));
+            }
+            context.add_command_line_option("-fprofile-use");
+            // NOTE: make sure gcc errors out when the profile data doesn't match the code instead
+            // of silently ignoring it.
+            context.add_command_line_option("-Werror=coverage-mismatch");
+        }
+
         if env::var("CG_GCCJIT_DUMP_CODE").as_deref() == Ok("1") {
             context.set_dump_code_on_compile(true);
         }
@@ -118,10 +204,23 @@ pub fn compile_codegen_unit<'tcx>(tcx: TyCtxt<'tcx>, cgu_name: Symbol, supports_
                 mono_item.define::<Builder<'_, '_, '_>>(&cx);
             }
 
+            // Mark the used statics, like cg_llvm does by creating the llvm.used variables.
+            if !cx.used_statics().borrow().is_empty() {
+                cx.create_used_variable();
+            }
+            if !cx.compiler_used_statics().borrow().is_empty() {
+                cx.create_compiler_used_variable();
+            }
+
             // If this codegen unit contains the main function, also create the
             // wrapper here
             maybe_create_entry_wrapper::<Builder<'_, '_, '_>>(&cx);
 
+            // Finalize code coverage by generating the functions that were not codegenned.
+            if cx.sess().instrument_coverage() {
+                cx.coverageinfo_finalize();
+            }
+
             // Finalize debuginfo
             if cx.sess().opts.debuginfo != DebugInfo::None {
                 cx.debuginfo_finalize();
@@ -131,7 +230,11 @@ pub fn compile_codegen_unit<'tcx>(tcx: TyCtxt<'tcx>, cgu_name: Symbol, supports_
         ModuleCodegen {
             name: cgu_name.to_string(),
             module_llvm: GccContext {
-                context
+                context,
+                should_combine_object_files: false,
+                temp_dir: None,
+                combined_outputs: vec![],
+                keep_in_memory_for_thin_lto: keep_in_memory_for_thin_lto(tcx.sess),
             },
             kind: ModuleKind::Regular,
         }
@@ -139,3 +242,20 @@ pub fn compile_codegen_unit<'tcx>(tcx: TyCtxt<'tcx>, cgu_name: Symbol, supports_
 
     (module, cost)
 }
+
+/// Set the base name of the gcov notes (.gcno) and data (.gcda) files of the codegen unit to a
+/// name that is stable across builds, in `dir` or in the output directory, and return it.
+///
+/// NOTE: libgccjit compiles the code in a temporary directory, so these files would otherwise be
+/// named after a temporary file.
+fn set_gcov_dump_base(context: &Context<'_>, tcx: TyCtxt<'_>, cgu_name: Symbol, dir: Option<&Path>) -> PathBuf {
+    let dump_base = tcx.output_filenames(()).temp_path_ext("", Some(cgu_name.as_str()));
+    let dump_base =
+        match dir {
+            Some(dir) => dir.join(dump_base.file_name().expect("file name")),
+            None => dump_base,
+        };
+    context.add_command_line_option("-dumpbase");
+    context.add_command_line_option(dump_base.to_str().expect("path to str"));
+    dump_base
+}
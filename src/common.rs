@@ -1,5 +1,5 @@
 use gccjit::LValue;
-use gccjit::{Block, RValue, Type, ToRValue};
+use gccjit::{Block, Location, RValue, Type, ToRValue};
 use rustc_codegen_ssa::mir::place::PlaceRef;
 use rustc_codegen_ssa::traits::{
     BaseTypeMethods,
@@ -46,25 +46,25 @@ impl<'gcc, 'tcx> CodegenCx<'gcc, 'tcx> {
         // TODO(antoyo): set linkage.
     }
 
-    pub fn inttoptr(&self, block: Block<'gcc>, value: RValue<'gcc>, dest_ty: Type<'gcc>) -> RValue<'gcc> {
+    pub fn inttoptr(&self, block: Block<'gcc>, loc: Option<Location<'gcc>>, value: RValue<'gcc>, dest_ty: Type<'gcc>) -> RValue<'gcc> {
         let func = block.get_function();
-        let local = func.new_local(None, value.get_type(), "intLocal");
-        block.add_assignment(None, local, value);
-        let value_address = local.get_address(None);
+        let local = func.new_local(loc, value.get_type(), "intLocal");
+        block.add_assignment(loc, local, value);
+        let value_address = local.get_address(loc);
 
-        let ptr = self.context.new_cast(None, value_address, dest_ty.make_pointer());
-        ptr.dereference(None).to_rvalue()
+        let ptr = self.context.new_cast(loc, value_address, dest_ty.make_pointer());
+        ptr.dereference(loc).to_rvalue()
     }
 
-    pub fn ptrtoint(&self, block: Block<'gcc>, value: RValue<'gcc>, dest_ty: Type<'gcc>) -> RValue<'gcc> {
+    pub fn ptrtoint(&self, block: Block<'gcc>, loc: Option<Location<'gcc>>, value: RValue<'gcc>, dest_ty: Type<'gcc>) -> RValue<'gcc> {
         // TODO(antoyo): when libgccjit allow casting from pointer to int, remove this.
         let func = block.get_function();
-        let local = func.new_local(None, value.get_type(), "ptrLocal");
-        block.add_assignment(None, local, value);
-        let ptr_address = local.get_address(None);
+        let local = func.new_local(loc, value.get_type(), "ptrLocal");
+        block.add_assignment(loc, local, value);
+        let ptr_address = local.get_address(loc);
 
-        let ptr = self.context.new_cast(None, ptr_address, dest_ty.make_pointer());
-        ptr.dereference(None).to_rvalue()
+        let ptr = self.context.new_cast(loc, ptr_address, dest_ty.make_pointer());
+        ptr.dereference(loc).to_rvalue()
     }
 }
 
@@ -203,7 +203,7 @@ impl<'gcc, 'tcx> ConstMethods<'tcx> for CodegenCx<'gcc, 'tcx> {
 
                 let value = self.const_uint_big(self.type_ix(bitsize), data);
                 if layout.value == Pointer {
-                    self.inttoptr(self.current_block.borrow().expect("block"), value, ty)
+                    self.inttoptr(self.current_block.borrow().expect("block"), None, value, ty)
                 } else {
                     self.const_bitcast(value, ty)
                 }
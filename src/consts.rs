@@ -1,4 +1,4 @@
-use gccjit::{GlobalKind, LValue, RValue, ToRValue, Type};
+use gccjit::{GlobalKind, LValue, RValue, Type, VarAttribute};
 use rustc_codegen_ssa::traits::{BaseTypeMethods, ConstMethods, DerivedTypeMethods, StaticMethods};
 use rustc_hir as hir;
 use rustc_hir::Node;
@@ -146,21 +146,26 @@ impl<'gcc, 'tcx> StaticMethods for CodegenCx<'gcc, 'tcx> {
                 unimplemented!();
             }
         } else {
-            // TODO(antoyo): set link section.
+            // NOTE: the link section was set when declaring the global (see predefine_static).
         }
 
         if attrs.flags.contains(CodegenFnAttrFlags::USED) {
-            self.add_used_global(global.to_rvalue());
+            let global_address = global.get_address(None);
+            self.global_lvalues.borrow_mut().insert(global_address, global);
+            self.add_used_global(global_address);
         }
     }
 
-    /// Add a global value to a list to be stored in the `llvm.used` variable, an array of i8*.
-    fn add_used_global(&self, _global: RValue<'gcc>) {
-        // TODO(antoyo)
+    /// Add a global value to a list of globals that will be marked with the `used` and `retain`
+    /// attributes by `create_used_variable()`.
+    fn add_used_global(&self, global: RValue<'gcc>) {
+        self.used_statics.borrow_mut().push(global);
     }
 
-    fn add_compiler_used_global(&self, _global: RValue<'gcc>) {
-        // TODO(antoyo)
+    /// Add a global value to a list of globals that will be marked with the `used` attribute by
+    /// `create_compiler_used_variable()`.
+    fn add_compiler_used_global(&self, global: RValue<'gcc>) {
+        self.compiler_used_statics.borrow_mut().push(global);
     }
 }
 
@@ -377,6 +382,9 @@ fn check_and_apply_linkage<'gcc, 'tcx>(cx: &CodegenCx<'gcc, 'tcx>, attrs: &Codeg
             };
         // Declare a symbol `foo` with the desired linkage.
         let global1 = cx.declare_global_with_linkage(&sym, llty2, base::global_linkage_to_gcc(linkage));
+        if base::is_weak_linkage(linkage) {
+            global1.add_attribute(VarAttribute::Weak);
+        }
 
         // Declare an internal global `extern_with_linkage_foo` which
         // is initialized with the address of `foo`.  If `foo` is
@@ -1,14 +1,15 @@
 use std::cell::{Cell, RefCell};
 
-use gccjit::{Block, CType, Context, Function, FunctionType, LValue, RValue, Struct, Type};
+use gccjit::{Block, CType, Context, Function, FunctionType, LValue, Location, RValue, Struct, Type, VarAttribute};
 use rustc_codegen_ssa::base::wants_msvc_seh;
 use rustc_codegen_ssa::traits::{
     BackendTypes,
+    BaseTypeMethods,
     MiscMethods,
 };
 use rustc_data_structures::base_n;
 use rustc_data_structures::fx::{FxHashMap, FxHashSet};
-use rustc_middle::span_bug;
+use rustc_middle::{bug, span_bug};
 use rustc_middle::mir::mono::CodegenUnit;
 use rustc_middle::ty::{self, Instance, ParamEnv, PolyExistentialTraitRef, Ty, TyCtxt};
 use rustc_middle::ty::layout::{FnAbiError, FnAbiOfHelpers, FnAbiRequest, HasParamEnv, HasTyCtxt, LayoutError, TyAndLayout, LayoutOfHelpers};
@@ -18,6 +19,7 @@ use rustc_target::abi::{call::FnAbi, HasDataLayout, PointeeInfo, Size, TargetDat
 use rustc_target::spec::{HasTargetSpec, Target, TlsModel};
 
 use crate::callee::get_fn;
+use crate::debuginfo::StackVar;
 use crate::declare::mangle_name;
 
 #[derive(Clone)]
@@ -95,12 +97,33 @@ pub struct CodegenCx<'gcc, 'tcx> {
     /// Cache of globals.
     pub globals: RefCell<FxHashMap<String, RValue<'gcc>>>,
 
+    /// Statics that will be marked as used so that they are kept by the compiler and the linker.
+    pub used_statics: RefCell<Vec<RValue<'gcc>>>,
+
+    /// Statics that will be marked as used so that they are kept by the compiler only.
+    pub compiler_used_statics: RefCell<Vec<RValue<'gcc>>>,
+
     /// A counter that is used for generating local symbol names
     local_gen_sym_counter: Cell<usize>,
     pub global_gen_sym_counter: Cell<usize>,
 
     eh_personality: Cell<Option<RValue<'gcc>>>,
 
+    /// The `__rust_try` shim used by the `try` intrinsic, with its function pointer type.
+    pub rust_try_fn: Cell<Option<(Type<'gcc>, Function<'gcc>)>>,
+
+    /// Landing pad blocks which are cleanups, so that `invoke()` generates a try/finally instead
+    /// of a try/catch for them.
+    pub cleanup_blocks: RefCell<FxHashSet<Block<'gcc>>>,
+
+    /// Prologue blocks of the functions compiled with debuginfo, with the start block of the
+    /// function, to which the prologue jumps (see create_function_debug_context()).
+    pub prologue_blocks: RefCell<FxHashMap<Function<'gcc>, (Block<'gcc>, Option<Block<'gcc>>)>>,
+    /// Variables allocated on the stack in the functions with a prologue block.
+    pub stack_vars: RefCell<Vec<StackVar<'gcc>>>,
+    /// Map from the pointer returned by alloca() to the index of its variable in `stack_vars`.
+    pub stack_var_indices: RefCell<FxHashMap<RValue<'gcc>, usize>>,
+
     pub pointee_infos: RefCell<FxHashMap<(Ty<'tcx>, Size), Option<PointeeInfo>>>,
 
     /// NOTE: a hack is used because the rustc API is not suitable to libgccjit and as such,
@@ -217,6 +240,8 @@ impl<'gcc, 'tcx> CodegenCx<'gcc, 'tcx> {
             global_lvalues: Default::default(),
             const_cstr_cache: Default::default(),
             globals: Default::default(),
+            used_statics: Default::default(),
+            compiler_used_statics: Default::default(),
             scalar_types: Default::default(),
             types: Default::default(),
             tcx,
@@ -225,6 +250,11 @@ impl<'gcc, 'tcx> CodegenCx<'gcc, 'tcx> {
             local_gen_sym_counter: Cell::new(0),
             global_gen_sym_counter: Cell::new(0),
             eh_personality: Cell::new(None),
+            rust_try_fn: Cell::new(None),
+            cleanup_blocks: Default::default(),
+            prologue_blocks: Default::default(),
+            stack_vars: Default::default(),
+            stack_var_indices: Default::default(),
             pointee_infos: Default::default(),
             structs_as_pointer: Default::default(),
         }
@@ -275,6 +305,13 @@ impl<'gcc, 'tcx> CodegenCx<'gcc, 'tcx> {
     pub fn sess(&self) -> &Session {
         &self.tcx.sess
     }
+
+    fn used_global_lvalue(&self, global: &RValue<'gcc>) -> LValue<'gcc> {
+        match self.global_lvalues.borrow().get(global) {
+            Some(&global) => global,
+            None => bug!("#[used] static {:?} was not defined by codegen_static", global),
+        }
+    }
 }
 
 impl<'gcc, 'tcx> BackendTypes for CodegenCx<'gcc, 'tcx> {
@@ -286,8 +323,8 @@ impl<'gcc, 'tcx> BackendTypes for CodegenCx<'gcc, 'tcx> {
     type Funclet = (); // TODO(antoyo)
 
     type DIScope = (); // TODO(antoyo)
-    type DILocation = (); // TODO(antoyo)
-    type DIVariable = (); // TODO(antoyo)
+    type DILocation = Location<'gcc>;
+    type DIVariable = (Symbol, Type<'gcc>);
 }
 
 impl<'gcc, 'tcx> MiscMethods<'tcx> for CodegenCx<'gcc, 'tcx> {
@@ -340,7 +377,8 @@ impl<'gcc, 'tcx> MiscMethods<'tcx> for CodegenCx<'gcc, 'tcx> {
         }
         let tcx = self.tcx;
         let llfn = match tcx.lang_items().eh_personality() {
-            Some(def_id) if !wants_msvc_seh(self.sess()) => self.get_fn_addr(
+            Some(def_id) if !wants_msvc_seh(self.sess()) => get_fn(
+                self,
                 ty::Instance::resolve(
                     tcx,
                     ty::ParamEnv::reveal_all(),
@@ -350,15 +388,12 @@ impl<'gcc, 'tcx> MiscMethods<'tcx> for CodegenCx<'gcc, 'tcx> {
                 .unwrap().unwrap(),
             ),
             _ => {
-                let _name = if wants_msvc_seh(self.sess()) {
+                let name = if wants_msvc_seh(self.sess()) {
                     "__CxxFrameHandler3"
                 } else {
                     "rust_eh_personality"
                 };
-                //let func = self.declare_func(name, self.type_i32(), &[], true);
-                // FIXME(antoyo): this hack should not be needed. That will probably be removed when
-                // unwinding support is added.
-                self.context.new_rvalue_from_int(self.int_type, 0)
+                self.declare_func(name, self.type_i32(), &[], true)
             }
         };
         // TODO(antoyo): apply target cpu attributes.
@@ -379,7 +414,7 @@ impl<'gcc, 'tcx> MiscMethods<'tcx> for CodegenCx<'gcc, 'tcx> {
     }
 
     fn used_statics(&self) -> &RefCell<Vec<RValue<'gcc>>> {
-        unimplemented!();
+        &self.used_statics
     }
 
     fn set_frame_pointer_type(&self, _llfn: RValue<'gcc>) {
@@ -387,11 +422,19 @@ impl<'gcc, 'tcx> MiscMethods<'tcx> for CodegenCx<'gcc, 'tcx> {
     }
 
     fn apply_target_cpu_attr(&self, _llfn: RValue<'gcc>) {
-        // TODO(antoyo)
+        // NOTE: the target CPU and features are set for the whole context (see
+        // add_target_options()), so there's no function attribute to add.
     }
 
     fn create_used_variable(&self) {
-        unimplemented!();
+        // NOTE: gcc doesn't have an equivalent to the llvm.used variable, so the attributes are
+        // set on the globals instead. The retain attribute prevents the linker from garbage
+        // collecting the section of the global when linking with --gc-sections.
+        for global in self.used_statics.borrow().iter() {
+            let global = self.used_global_lvalue(global);
+            global.add_attribute(VarAttribute::Used);
+            global.add_attribute(VarAttribute::Retain);
+        }
     }
 
     fn declare_c_main(&self, fn_type: Self::Type) -> Option<Self::Function> {
@@ -407,11 +450,14 @@ impl<'gcc, 'tcx> MiscMethods<'tcx> for CodegenCx<'gcc, 'tcx> {
     }
 
     fn compiler_used_statics(&self) -> &RefCell<Vec<RValue<'gcc>>> {
-        unimplemented!()
+        &self.compiler_used_statics
     }
 
     fn create_compiler_used_variable(&self) {
-        unimplemented!()
+        for global in self.compiler_used_statics.borrow().iter() {
+            let global = self.used_global_lvalue(global);
+            global.add_attribute(VarAttribute::Used);
+        }
     }
 }
 
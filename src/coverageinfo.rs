@@ -1,6 +1,9 @@
-use gccjit::RValue;
+use gccjit::{FunctionType, RValue};
 use rustc_codegen_ssa::traits::{CoverageInfoBuilderMethods, CoverageInfoMethods};
+use rustc_data_structures::fx::FxHashSet;
+use rustc_hir::def::DefKind;
 use rustc_hir::def_id::DefId;
+use rustc_middle::middle::codegen_fn_attrs::CodegenFnAttrFlags;
 use rustc_middle::mir::coverage::{
     CodeRegion,
     CounterValueReference,
@@ -8,10 +11,19 @@ use rustc_middle::mir::coverage::{
     InjectedExpressionId,
     Op,
 };
+use rustc_middle::ty;
 use rustc_middle::ty::Instance;
+use rustc_middle::ty::subst::InternalSubsts;
 
 use crate::builder::Builder;
 use crate::context::CodegenCx;
+use crate::declare::{mangle_name, set_symbol_name};
+
+// NOTE: the code coverage is implemented with the gcov instrumentation of GCC (-fprofile-arcs and
+// -ftest-coverage, see base::compile_codegen_unit) instead of the counters injected in the MIR,
+// which are tied to the LLVM coverage mapping format. gcov maps the execution counts back to the
+// source lines using the locations of the statements, so the reports can be generated with gcov,
+// lcov or gcovr.
 
 impl<'a, 'gcc, 'tcx> CoverageInfoBuilderMethods<'tcx> for Builder<'a, 'gcc, 'tcx> {
     fn set_function_source_hash(
@@ -19,51 +31,120 @@ impl<'a, 'gcc, 'tcx> CoverageInfoBuilderMethods<'tcx> for Builder<'a, 'gcc, 'tcx
         _instance: Instance<'tcx>,
         _function_source_hash: u64,
     ) -> bool {
-        unimplemented!();
+        // NOTE: returning false tells rustc_codegen_ssa to not inject the MIR counters since gcc
+        // already instruments the code.
+        false
     }
 
     fn add_coverage_counter(&mut self, _instance: Instance<'tcx>, _id: CounterValueReference, _region: CodeRegion) -> bool {
-        // TODO(antoyo)
         false
     }
 
     fn add_coverage_counter_expression(&mut self, _instance: Instance<'tcx>, _id: InjectedExpressionId, _lhs: ExpressionOperandId, _op: Op, _rhs: ExpressionOperandId, _region: Option<CodeRegion>) -> bool {
-        // TODO(antoyo)
         false
     }
 
     fn add_coverage_unreachable(&mut self, _instance: Instance<'tcx>, _region: CodeRegion) -> bool {
-        // TODO(antoyo)
         false
     }
 }
 
 impl<'gcc, 'tcx> CoverageInfoMethods<'tcx> for CodegenCx<'gcc, 'tcx> {
     fn coverageinfo_finalize(&self) {
-        // TODO(antoyo)
+        // In order to show that unused functions have coverage counts of zero (0), gcov requires
+        // the functions to exist. Generate synthetic functions covering the MIR `Coverage` code
+        // regions of the functions that were not codegenned.
+        if self.codegen_unit.is_code_coverage_dead_code_cgu() {
+            add_unused_functions(self);
+        }
     }
 
     fn get_pgo_func_name_var(&self, _instance: Instance<'tcx>) -> RValue<'gcc> {
-        unimplemented!();
+        unreachable!("no MIR counters are injected since gcc instruments the code");
     }
 
     /// Functions with MIR-based coverage are normally codegenned _only_ if
-    /// called. LLVM coverage tools typically expect every function to be
-    /// defined (even if unused), with at least one call to LLVM intrinsic
-    /// `instrprof.increment`.
+    /// called. gcov only reports the functions that are compiled, so unused
+    /// functions would be missing from the coverage reports.
     ///
-    /// Codegen a small function that will never be called, with one counter
-    /// that will never be incremented.
-    ///
-    /// For used/called functions, the coverageinfo was already added to the
-    /// `function_coverage_map` (keyed by function `Instance`) during codegen.
-    /// But in this case, since the unused function was _not_ previously
-    /// codegenned, collect the coverage `CodeRegion`s from the MIR and add
-    /// them. The first `CodeRegion` is used to add a single counter, with the
-    /// same counter ID used in the injected `instrprof.increment` intrinsic
-    /// call. Since the function is never called, all other `CodeRegion`s can be
-    /// added as `unreachable_region`s.
-    fn define_unused_fn(&self, _def_id: DefId) {
-        unimplemented!();
+    /// Codegen a small function that will never be called, with one statement
+    /// located at the start of each `CodeRegion` of the MIR of the unused
+    /// function, so that gcov reports these lines as never executed.
+    fn define_unused_fn(&self, def_id: DefId) {
+        let tcx = self.tcx;
+        let instance = Instance::new(
+            def_id,
+            InternalSubsts::for_item(tcx, def_id, |param, _| {
+                if let ty::GenericParamDefKind::Lifetime = param.kind {
+                    tcx.lifetimes.re_erased.into()
+                }
+                else {
+                    tcx.mk_param_from_def(param)
+                }
+            }),
+        );
+
+        let regions = tcx.covered_code_regions(def_id);
+        let location = |region: &CodeRegion|
+            self.context.new_location(region.file_name.as_str(), region.start_line as i32, region.start_col as i32);
+
+        // NOTE: the function is internal and never called, so the code coverage dead code cgu is
+        // compiled with -fkeep-static-functions to keep it when optimizations are enabled.
+        let symbol_name = tcx.symbol_name(instance).name;
+        let func = self.context.new_function(regions.first().map(|region| location(region)), FunctionType::Internal, self.type_void(), &[], mangle_name(symbol_name), false);
+        set_symbol_name(func, symbol_name);
+        let block = func.new_block("unused_function");
+        let unused = func.new_local(None, self.int_type, "unused");
+        for region in regions {
+            block.add_assignment(Some(location(region)), unused, self.context.new_rvalue_zero(self.int_type));
+        }
+        block.end_with_void_return(None);
+    }
+}
+
+/// Calls `define_unused_fn` for the functions that were never codegenned or inlined, i.e. the
+/// `DefId`s of the functions in `mir_keys` that are not in `codegened_and_inlined_items`.
+///
+/// These unused functions are then codegenned in the CGU which is marked as the "code coverage
+/// dead code cgu" during the partitioning process, so that they are only defined once.
+fn add_unused_functions(cx: &CodegenCx<'_, '_>) {
+    let tcx = cx.tcx;
+
+    let ignore_unused_generics = tcx.sess.instrument_coverage_except_unused_generics();
+
+    let eligible_def_ids: FxHashSet<DefId> = tcx
+        .mir_keys(())
+        .iter()
+        .filter_map(|local_def_id| {
+            let def_id = local_def_id.to_def_id();
+            let kind = tcx.def_kind(def_id);
+            // `mir_keys` will give us `DefId`s for all kinds of things, not
+            // just "functions", like consts, statics, etc. Filter those out.
+            // If `ignore_unused_generics` was specified, filter out any
+            // generic functions from consideration as well.
+            let is_function = matches!(kind, DefKind::Fn | DefKind::AssocFn | DefKind::Closure | DefKind::Generator);
+            if !is_function || (ignore_unused_generics && tcx.generics_of(def_id).requires_monomorphization(tcx)) {
+                return None;
+            }
+            Some(def_id)
+        })
+        .collect();
+
+    let codegenned_def_ids = tcx.codegened_and_inlined_items(());
+
+    // Sort the functions to ensure we produce deterministic results.
+    let mut unused_def_ids: Vec<_> = eligible_def_ids.difference(codegenned_def_ids).copied().collect();
+    unused_def_ids.sort_by_cached_key(|&def_id| tcx.def_path_hash(def_id));
+
+    for non_codegenned_def_id in unused_def_ids {
+        let codegen_fn_attrs = tcx.codegen_fn_attrs(non_codegenned_def_id);
+
+        // If a function is marked `#[no_coverage]`, then skip generating a
+        // dead code stub for it.
+        if codegen_fn_attrs.flags.contains(CodegenFnAttrFlags::NO_COVERAGE) {
+            continue;
+        }
+
+        cx.define_unused_fn(non_codegenned_def_id);
     }
 }
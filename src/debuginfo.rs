@@ -1,62 +1,247 @@
-use gccjit::RValue;
-use rustc_codegen_ssa::mir::debuginfo::{FunctionDebugContext, VariableKind};
-use rustc_codegen_ssa::traits::{DebugInfoBuilderMethods, DebugInfoMethods};
+use gccjit::{Function, LValue, Location, RValue, ToRValue, Type};
+use rustc_codegen_ssa::mir::debuginfo::{DebugScope, FunctionDebugContext, VariableKind};
+use rustc_codegen_ssa::traits::{BaseTypeMethods, BuilderMethods, ConstMethods, DebugInfoBuilderMethods, DebugInfoMethods, DerivedTypeMethods};
 use rustc_middle::mir;
 use rustc_middle::ty::{Instance, PolyExistentialTraitRef, Ty};
-use rustc_span::{SourceFile, Span, Symbol};
-use rustc_target::abi::Size;
+use rustc_middle::ty::layout::LayoutOf;
+use rustc_session::config::DebugInfo;
+use rustc_span::{BytePos, Pos, SourceFile, SourceFileAndLine, Span, Symbol, sym};
+use rustc_target::abi::{HasDataLayout, Size};
 use rustc_target::abi::call::FnAbi;
 
 use crate::builder::Builder;
 use crate::context::CodegenCx;
+use crate::type_of::LayoutGccExt;
 
 impl<'a, 'gcc, 'tcx> DebugInfoBuilderMethods for Builder<'a, 'gcc, 'tcx> {
     // FIXME(eddyb) find a common convention for all of the debuginfo-related
     // names (choose between `dbg`, `debug`, `debuginfo`, `debug_info` etc.).
-    fn dbg_var_addr(&mut self, _dbg_var: Self::DIVariable, _scope_metadata: Self::DIScope, _variable_alloca: Self::Value, _direct_offset: Size, _indirect_offsets: &[Size]) {
-        unimplemented!();
+    fn dbg_var_addr(&mut self, dbg_var: Self::DIVariable, dbg_loc: Self::DILocation, variable_alloca: Self::Value, direct_offset: Size, indirect_offsets: &[Size]) {
+        let (name, typ) = dbg_var;
+        if direct_offset == Size::ZERO && indirect_offsets.is_empty() && self.declare_stack_var(variable_alloca, name, typ, dbg_loc) {
+            return;
+        }
+
+        // NOTE: the other variables are a part of a stack variable or are accessed through a
+        // pointer. Since gccjit has no equivalent to the DWARF expressions describing these
+        // locations, a pointer to the variable is declared with the name of the variable, so that
+        // it can be printed in a debugger with `print *name`.
+        let address = self.offset_address(variable_alloca, direct_offset);
+        // NOTE: the indirect offsets are used for the variables which are accessed through a
+        // pointer, like the variables captured by reference by a closure. Since there's no
+        // equivalent to the DWARF expressions dereferencing the pointers in gccjit, the pointers
+        // are dereferenced here. This is correct because the variables are introduced at the start
+        // of the function, after the arguments containing these pointers have been stored.
+        let address = indirect_offsets.iter().fold(address, |address, &offset| {
+            let pointer_type = self.type_i8p();
+            let address = self.pointercast(address, self.type_ptr_to(pointer_type));
+            let pointer = self.load(pointer_type, address, self.data_layout().pointer_align.abi);
+            self.offset_address(pointer, offset)
+        });
+        let address = self.pointercast(address, typ.make_pointer());
+        let variable = self.current_func().new_local(Some(dbg_loc), typ.make_pointer(), name.as_str());
+        self.llbb().add_assignment(Some(dbg_loc), variable, address);
     }
 
+    /// Inserts a side-effect free instruction sequence that makes sure that the
+    /// .debug_gdb_scripts global is referenced, so it isn't removed by the linker.
     fn insert_reference_to_gdb_debug_scripts_section_global(&mut self) {
-        // TODO(antoyo): insert reference to gdb debug scripts section global.
+        if needs_gdb_debug_scripts_section(self) {
+            let gdb_debug_scripts_section = self.cx.get_or_insert_gdb_debug_scripts_section_global();
+            let gdb_debug_scripts_section = self.pointercast(gdb_debug_scripts_section, self.type_i8p());
+            // Load just the first byte as that's all that's necessary to force gcc to keep around
+            // the reference to the global.
+            let first_byte = self.volatile_load(self.type_i8(), gdb_debug_scripts_section);
+            self.llbb().add_eval(self.location, first_byte);
+        }
     }
 
     fn set_var_name(&mut self, _value: RValue<'gcc>, _name: &str) {
-        unimplemented!();
+        // NOTE: gccjit cannot rename a value once it is created. The names of the variables are
+        // handled in dbg_var_addr().
+    }
+
+    fn set_dbg_loc(&mut self, dbg_loc: Self::DILocation) {
+        self.location = Some(dbg_loc);
+    }
+}
+
+/// Variable allocated on the stack by alloca() in a function with a prologue block.
+pub struct StackVar<'gcc> {
+    function: Function<'gcc>,
+    /// Pointer returned by alloca(), which is assigned the address of the variable in the
+    /// prologue.
+    pointer: LValue<'gcc>,
+    typ: Type<'gcc>,
+    aligned_type: Type<'gcc>,
+    index: usize,
+    /// Whether the variable was declared with the name of a Rust variable by dbg_var_addr().
+    declared: bool,
+}
+
+impl<'a, 'gcc, 'tcx> Builder<'a, 'gcc, 'tcx> {
+    /// Allocate a variable on the stack in a function with a prologue block and return a pointer
+    /// to it.
+    ///
+    /// NOTE: gccjit cannot rename a local, so the local created by alloca() could not get the name
+    /// of the Rust variable that dbg_var_addr() later introduces. Instead, alloca() returns a
+    /// pointer which is assigned the address of the variable in the prologue, so that the
+    /// variable can be declared later with the right name. With optimizations, gcc replaces the
+    /// pointer by the address of the variable.
+    pub fn alloca_in_prologue(&mut self, typ: Type<'gcc>, aligned_type: Type<'gcc>, index: usize) -> Option<RValue<'gcc>> {
+        let function = self.current_func();
+        if !self.cx.prologue_blocks.borrow().contains_key(&function) {
+            return None;
+        }
+
+        let pointer = function.new_local(self.location, aligned_type.make_pointer(), &format!("stack_var_ptr_{}", index));
+        let address = pointer.to_rvalue();
+        let mut stack_vars = self.cx.stack_vars.borrow_mut();
+        self.cx.stack_var_indices.borrow_mut().insert(address, stack_vars.len());
+        stack_vars.push(StackVar { function, pointer, typ, aligned_type, index, declared: false });
+        Some(address)
+    }
+
+    /// Declare the stack variable allocated by alloca_in_prologue() with the name of the Rust
+    /// variable, so that it can be printed in a debugger with `print name`.
+    fn declare_stack_var(&mut self, address: RValue<'gcc>, name: Symbol, typ: Type<'gcc>, dbg_loc: Location<'gcc>) -> bool {
+        let index =
+            match self.cx.stack_var_indices.borrow().get(&address) {
+                Some(&index) => index,
+                None => return false,
+            };
+        let mut stack_vars = self.cx.stack_vars.borrow_mut();
+        let stack_var = &mut stack_vars[index];
+        if stack_var.declared || stack_var.typ != typ {
+            return false;
+        }
+
+        let (prologue_block, _) = self.cx.prologue_blocks.borrow()[&stack_var.function];
+        let variable = stack_var.function.new_local(Some(dbg_loc), stack_var.aligned_type, name.as_str());
+        prologue_block.add_assignment(None, stack_var.pointer, variable.get_address(None));
+        stack_var.declared = true;
+        true
     }
 
-    fn set_dbg_loc(&mut self, _dbg_loc: Self::DILocation) {
-        unimplemented!();
+    fn offset_address(&mut self, address: RValue<'gcc>, offset: Size) -> RValue<'gcc> {
+        if offset == Size::ZERO {
+            return address;
+        }
+        let address_i8 = self.pointercast(address, self.type_i8p());
+        self.inbounds_gep(self.type_i8(), address_i8, &[self.const_usize(offset.bytes())])
     }
 }
 
 impl<'gcc, 'tcx> DebugInfoMethods<'tcx> for CodegenCx<'gcc, 'tcx> {
     fn create_vtable_metadata(&self, _ty: Ty<'tcx>, _trait_ref: Option<PolyExistentialTraitRef<'tcx>>, _vtable: Self::Value) {
-        // TODO(antoyo)
+        // NOTE: gccjit has no way to describe the type a vtable is for, so the vtables are only
+        // constant data and the vtable field of the trait objects is a plain pointer.
     }
 
-    fn create_function_debug_context(&self, _instance: Instance<'tcx>, _fn_abi: &FnAbi<'tcx, Ty<'tcx>>, _llfn: RValue<'gcc>, _mir: &mir::Body<'tcx>) -> Option<FunctionDebugContext<Self::DIScope, Self::DILocation>> {
-        // TODO(antoyo)
-        None
+    fn create_function_debug_context(&self, _instance: Instance<'tcx>, _fn_abi: &FnAbi<'tcx, Ty<'tcx>>, llfn: RValue<'gcc>, mir: &mir::Body<'tcx>) -> Option<FunctionDebugContext<Self::DIScope, Self::DILocation>> {
+        // NOTE: the locations are also needed by gcov to map the coverage counters to the source.
+        if self.sess().opts.debuginfo == DebugInfo::None && !self.sess().instrument_coverage() {
+            return None;
+        }
+
+        if self.sess().opts.debuginfo != DebugInfo::None {
+            // NOTE: this is called before the start block is created, so the prologue block is the
+            // entry block of the function. The addresses of the stack variables are assigned in it
+            // (see alloca_in_prologue()) and it jumps to the start block (see
+            // debuginfo_finalize()).
+            let function = self.rvalue_as_function(llfn);
+            let prologue_block = function.new_block("prologue");
+            self.prologue_blocks.borrow_mut().insert(function, (prologue_block, None));
+        }
+
+        // NOTE: gccjit locations only contain a file name, a line and a column, so the scopes are
+        // only needed for rustc_codegen_ssa to get the locations of the statements. Since gccjit
+        // has no inlined locations either, the statements of the functions inlined by the MIR
+        // inliner get the location of their source without any reference to the call site.
+        let scopes = mir.source_scopes.iter()
+            .map(|_| DebugScope {
+                dbg_scope: Some(()),
+                inlined_at: None,
+                // NOTE: since the locations contain the file name, the scopes don't need to be
+                // extended to other files.
+                file_start_pos: BytePos(0),
+                file_end_pos: BytePos(u32::MAX),
+            })
+            .collect();
+        Some(FunctionDebugContext { scopes })
     }
 
-    fn extend_scope_to_file(&self, _scope_metadata: Self::DIScope, _file: &SourceFile) -> Self::DIScope {
-        unimplemented!();
+    fn extend_scope_to_file(&self, scope_metadata: Self::DIScope, _file: &SourceFile) -> Self::DIScope {
+        scope_metadata
     }
 
     fn debuginfo_finalize(&self) {
-        // TODO(antoyo)
+        // NOTE: the stack variables that don't hold a Rust variable are declared with a generic
+        // name.
+        let prologue_blocks = self.prologue_blocks.borrow();
+        for stack_var in self.stack_vars.borrow().iter().filter(|stack_var| !stack_var.declared) {
+            let (prologue_block, _) = prologue_blocks[&stack_var.function];
+            let variable = stack_var.function.new_local(None, stack_var.aligned_type, &format!("stack_var_{}", stack_var.index));
+            prologue_block.add_assignment(None, stack_var.pointer, variable.get_address(None));
+        }
+
+        for &(prologue_block, start_block) in prologue_blocks.values() {
+            let start_block = start_block.expect("start block of the function");
+            prologue_block.end_with_jump(None, start_block);
+        }
     }
 
-    fn create_dbg_var(&self, _variable_name: Symbol, _variable_type: Ty<'tcx>, _scope_metadata: Self::DIScope, _variable_kind: VariableKind, _span: Span) -> Self::DIVariable {
-        unimplemented!();
+    fn create_dbg_var(&self, variable_name: Symbol, variable_type: Ty<'tcx>, _scope_metadata: Self::DIScope, _variable_kind: VariableKind, _span: Span) -> Self::DIVariable {
+        let typ = self.layout_of(variable_type).gcc_type(self, true);
+        (variable_name, typ)
     }
 
     fn dbg_scope_fn(&self, _instance: Instance<'tcx>, _fn_abi: &FnAbi<'tcx, Ty<'tcx>>, _maybe_definition_llfn: Option<RValue<'gcc>>) -> Self::DIScope {
-        unimplemented!();
+        // NOTE: the scope of a function is implicit in gccjit.
     }
 
-    fn dbg_loc(&self, _scope: Self::DIScope, _inlined_at: Option<Self::DILocation>, _span: Span) -> Self::DILocation {
-        unimplemented!();
+    fn dbg_loc(&self, _scope: Self::DIScope, _inlined_at: Option<Self::DILocation>, span: Span) -> Self::DILocation {
+        let pos = span.lo();
+        let (file, line, col) =
+            match self.sess().source_map().lookup_line(pos) {
+                Ok(SourceFileAndLine { sf: file, line }) => {
+                    let line_pos = file.line_begin_pos(pos);
+                    // NOTE: gcc uses 1-based indexing.
+                    let line = (line + 1) as i32;
+                    let col = (pos - line_pos).to_u32() as i32 + 1;
+                    (file, line, col)
+                },
+                Err(file) => (file, 0, 0),
+            };
+        self.context.new_location(file.name.prefer_remapped().to_string(), line, col)
     }
 }
+
+impl<'gcc, 'tcx> CodegenCx<'gcc, 'tcx> {
+    /// Allocates the global variable responsible for the .debug_gdb_scripts binary
+    /// section.
+    fn get_or_insert_gdb_debug_scripts_section_global(&self) -> RValue<'gcc> {
+        let section_var_name = "__rustc_debug_gdb_scripts_section__";
+        if let Some(section_var) = self.get_declared_value(section_var_name) {
+            return section_var;
+        }
+
+        let section_contents = self.const_bytes(b"\x01gdb_load_rust_pretty_printers.py\0");
+        // NOTE: gccjit doesn't support the linkonce linkage used by cg_llvm, so the global is
+        // private and is only inserted in the codegen unit containing the main function, where it
+        // is referenced. That's why it is not inserted in debuginfo_finalize() like cg_llvm does.
+        let section_var = self.declare_private_global(section_var_name, section_contents.get_type());
+        section_var.set_link_section(".debug_gdb_scripts");
+        section_var.global_set_initializer_rvalue(section_contents);
+        section_var.get_address(None)
+    }
+}
+
+fn needs_gdb_debug_scripts_section(cx: &CodegenCx<'_, '_>) -> bool {
+    let omit_gdb_pretty_printer_section =
+        cx.tcx.sess.contains_name(cx.tcx.hir().krate_attrs(), sym::omit_gdb_pretty_printer_section);
+
+    !omit_gdb_pretty_printer_section
+        && cx.sess().opts.debuginfo != DebugInfo::None
+        && cx.sess().target.emit_debug_gdb_scripts
+}
@@ -40,12 +40,12 @@ impl<'gcc, 'tcx> CodegenCx<'gcc, 'tcx> {
         global
     }
 
-    /*pub fn declare_func(&self, name: &str, return_type: Type<'gcc>, params: &[Type<'gcc>], variadic: bool) -> RValue<'gcc> {
-        self.linkage.set(FunctionType::Exported);
+    pub fn declare_func(&self, name: &str, return_type: Type<'gcc>, params: &[Type<'gcc>], variadic: bool) -> RValue<'gcc> {
+        self.linkage.set(FunctionType::Extern);
         let func = declare_raw_fn(self, name, () /*llvm::CCallConv*/, return_type, params, variadic);
         // FIXME(antoyo): this is a wrong cast. That requires changing the compiler API.
         unsafe { std::mem::transmute(func) }
-    }*/
+    }
 
     pub fn declare_global(&self, name: &str, ty: Type<'gcc>, global_kind: GlobalKind, is_tls: bool, link_section: Option<Symbol>) -> LValue<'gcc> {
         let global = self.context.new_global(None, global_kind, ty, name);
@@ -115,6 +115,7 @@ fn declare_raw_fn<'gcc>(cx: &CodegenCx<'gcc, '_>, name: &str, _callconv: () /*ll
                 .map(|(index, param)| cx.context.new_parameter(None, *param, &format!("param{}", index))) // TODO(antoyo): set name.
                 .collect();
             let func = cx.context.new_function(None, cx.linkage.get(), return_type, &params, mangle_name(name), variadic);
+            set_symbol_name(func, name);
             cx.functions.borrow_mut().insert(name.to_string(), func);
             func
         };
@@ -129,8 +130,10 @@ fn declare_raw_fn<'gcc>(cx: &CodegenCx<'gcc, '_>, name: &str, _callconv: () /*ll
     func
 }
 
-// FIXME(antoyo): this is a hack because libgccjit currently only supports alpha, num and _.
-// Unsupported characters: `$` and `.`.
+// NOTE: libgccjit only supports alpha, num and _ in the function names, so the unsupported
+// characters (`$` and `.`) are replaced in the name of the function and set_symbol_name() is
+// called to emit the exact symbol name.
+// The names of the globals are not restricted, so they are used as is.
 pub fn mangle_name(name: &str) -> String {
     name.replace(|char: char| {
         if !char.is_alphanumeric() && char != '_' {
@@ -142,3 +145,11 @@ pub fn mangle_name(name: &str) -> String {
         }
     }, "_")
 }
+
+/// Set the assembler name of a function created with the name returned by `mangle_name(name)`,
+/// so that the symbol is the same as the one emitted by the LLVM backend.
+pub fn set_symbol_name(func: Function<'_>, name: &str) {
+    if mangle_name(name) != name {
+        func.set_asm_name(name);
+    }
+}
@@ -1,18 +1,17 @@
 /*
  * TODO(antoyo): implement equality in libgccjit based on https://zpz.github.io/blog/overloading-equality-operator-in-cpp-class-hierarchy/ (for type equality?)
- * TODO(antoyo): support #[inline] attributes.
- * TODO(antoyo): support LTO (gcc's equivalent to Thin LTO is enabled by -fwhopr: https://stackoverflow.com/questions/64954525/does-gcc-have-thin-lto).
  *
  * TODO(antoyo): remove the patches.
  */
 
-#![feature(rustc_private, decl_macro, associated_type_bounds, never_type, trusted_len)]
+#![feature(rustc_private, decl_macro, associated_type_bounds, never_type, trusted_len, once_cell)]
 #![allow(broken_intra_doc_links)]
 #![recursion_limit="256"]
 #![warn(rust_2018_idioms)]
 #![warn(unused_lifetimes)]
 
 extern crate rustc_ast;
+extern crate rustc_attr;
 extern crate rustc_codegen_ssa;
 extern crate rustc_data_structures;
 extern crate rustc_errors;
@@ -32,6 +31,7 @@ mod abi;
 mod allocator;
 mod archive;
 mod asm;
+mod attributes;
 mod back;
 mod base;
 mod builder;
@@ -49,27 +49,35 @@ mod type_;
 mod type_of;
 
 use std::any::Any;
+use std::lazy::SyncOnceCell;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
 use std::sync::{Arc, Mutex};
 
 use gccjit::{Context, OptimizationLevel, CType};
 use rustc_ast::expand::allocator::AllocatorKind;
-use rustc_codegen_ssa::{CodegenResults, CompiledModule, ModuleCodegen};
+use rustc_codegen_ssa::{CodegenResults, CompiledModule, ModuleCodegen, NativeLib};
+use rustc_codegen_ssa::back::link::linker_and_flavor;
 use rustc_codegen_ssa::base::codegen_crate;
-use rustc_codegen_ssa::back::write::{CodegenContext, FatLTOInput, ModuleConfig, TargetMachineFactoryFn};
+use rustc_codegen_ssa::back::write::{CodegenContext, FatLTOInput, ModuleConfig, TargetMachineFactoryConfig, TargetMachineFactoryFn};
 use rustc_codegen_ssa::back::lto::{LtoModuleCodegen, SerializedModule, ThinModule};
 use rustc_codegen_ssa::target_features::supported_target_features;
-use rustc_codegen_ssa::traits::{CodegenBackend, ExtraBackendMethods, ModuleBufferMethods, ThinBufferMethods, WriteBackendMethods};
-use rustc_data_structures::fx::FxHashMap;
+use rustc_codegen_ssa::traits::{CodegenBackend, ExtraBackendMethods, WriteBackendMethods};
+use rustc_data_structures::fx::{FxHashMap, FxHashSet};
 use rustc_errors::{ErrorReported, Handler};
 use rustc_metadata::EncodedMetadata;
 use rustc_middle::dep_graph::{WorkProduct, WorkProductId};
 use rustc_middle::ty::TyCtxt;
-use rustc_session::config::{Lto, OptLevel, OutputFilenames};
+use rustc_session::config::{CrateType, OptLevel, OutputFilenames, OutputType, PrintRequest};
 use rustc_session::Session;
+use rustc_session::utils::NativeLibKind;
 use rustc_span::Symbol;
 use rustc_span::fatal_error::FatalError;
+use rustc_target::spec::LinkerFlavor;
 use tempfile::TempDir;
 
+use crate::back::lto::{ModuleBuffer, ThinBuffer, ThinData};
+
 pub struct PrintOnPanic<F: Fn() -> String>(pub F);
 
 impl<F: Fn() -> String> Drop for PrintOnPanic<F> {
@@ -87,8 +95,10 @@ pub struct GccCodegenBackend {
 
 impl CodegenBackend for GccCodegenBackend {
     fn init(&self, sess: &Session) {
-        if sess.lto() != Lto::No {
-            sess.warn("LTO is not supported. You may get a linker error.");
+        if sess.opts.debugging_opts.combine_cgu && sess.opts.output_types.contains_key(&OutputType::Assembly) {
+            // NOTE: the codegen units are combined by a relocatable link of their object files (see
+            // back::write::link), so there's no assembly of the combined module.
+            sess.fatal("-Zcombine-cgu is not supported with --emit=asm");
         }
 
         let temp_dir = TempDir::new().expect("cannot create temporary directory");
@@ -117,9 +127,32 @@ impl CodegenBackend for GccCodegenBackend {
         Ok((codegen_results, work_products))
     }
 
-    fn link(&self, sess: &Session, codegen_results: CodegenResults, outputs: &OutputFilenames) -> Result<(), ErrorReported> {
+    fn link(&self, sess: &Session, mut codegen_results: CodegenResults, outputs: &OutputFilenames) -> Result<(), ErrorReported> {
         use rustc_codegen_ssa::back::link::link_binary;
 
+        let links_output = sess.crate_types().iter()
+            .any(|crate_type| !matches!(crate_type, CrateType::Rlib | CrateType::Staticlib));
+        if sess.opts.cg.linker_plugin_lto.enabled() && links_output {
+            // NOTE: rustc passes the options of the LLVM linker plugin, which the GCC LTO plugin
+            // doesn't understand, so the linker must be the wrapper that converts them.
+            let (linker, _) = linker_and_flavor(sess);
+            if linker.file_stem().and_then(|stem| stem.to_str()) != Some("linker_plugin_lto") {
+                sess.fatal("-Clinker-plugin-lto requires -Clinker=path/to/linker_plugin_lto.sh from rustc_codegen_gcc");
+            }
+        }
+
+        if needs_gcov_runtime(sess) {
+            // NOTE: the code instrumented by gcc calls the functions of the gcov runtime, which is
+            // not linked by the rustc linker invocation.
+            codegen_results.crate_info.used_libraries.push(NativeLib {
+                kind: NativeLibKind::Static { bundle: Some(false), whole_archive: None },
+                name: Some(Symbol::intern("gcov")),
+                cfg: None,
+                verbatim: None,
+                dll_imports: vec![],
+            });
+        }
+
         link_binary::<crate::archive::ArArchiveBuilder<'_>>(
             sess,
             &codegen_results,
@@ -127,15 +160,27 @@ impl CodegenBackend for GccCodegenBackend {
         )
     }
 
+    fn print(&self, req: PrintRequest, sess: &Session) {
+        if req == PrintRequest::TargetCPUs {
+            print_target_cpus(sess);
+        }
+    }
+
     fn target_features(&self, sess: &Session) -> Vec<Symbol> {
         target_features(sess)
     }
 }
 
 impl ExtraBackendMethods for GccCodegenBackend {
-    fn new_metadata<'tcx>(&self, _tcx: TyCtxt<'tcx>, _mod_name: &str) -> Self::Module {
+    fn new_metadata<'tcx>(&self, tcx: TyCtxt<'tcx>, _mod_name: &str) -> Self::Module {
+        let context = Context::default();
+        add_target_options(&context, tcx.sess);
         GccContext {
-            context: Context::default(),
+            context,
+            should_combine_object_files: false,
+            temp_dir: None,
+            combined_outputs: vec![],
+            keep_in_memory_for_thin_lto: false,
         }
     }
 
@@ -147,41 +192,49 @@ impl ExtraBackendMethods for GccCodegenBackend {
         base::compile_codegen_unit(tcx, cgu_name, *self.supports_128bit_integers.lock().expect("lock"))
     }
 
-    fn target_machine_factory(&self, _sess: &Session, _opt_level: OptLevel) -> TargetMachineFactoryFn<Self> {
-        // TODO(antoyo): set opt level.
-        Arc::new(|_| {
-            Ok(())
+    fn target_machine_factory(&self, sess: &Session, opt_level: OptLevel) -> TargetMachineFactoryFn<Self> {
+        let target_options = target_options(sess).to_vec();
+        Arc::new(move |_| {
+            Ok(TargetMachine {
+                opt_level,
+                target_options: target_options.clone(),
+            })
         })
     }
 
-    fn target_cpu<'b>(&self, _sess: &'b Session) -> &'b str {
-        unimplemented!();
-    }
-
-    fn tune_cpu<'b>(&self, _sess: &'b Session) -> Option<&'b str> {
-        None
-        // TODO(antoyo)
+    fn target_cpu<'b>(&self, sess: &'b Session) -> &'b str {
+        target_cpu(sess)
     }
-}
-
-pub struct ModuleBuffer;
 
-impl ModuleBufferMethods for ModuleBuffer {
-    fn data(&self) -> &[u8] {
-        unimplemented!();
+    fn tune_cpu<'b>(&self, sess: &'b Session) -> Option<&'b str> {
+        tune_cpu(sess)
     }
 }
 
-pub struct ThinBuffer;
-
-impl ThinBufferMethods for ThinBuffer {
-    fn data(&self) -> &[u8] {
-        unimplemented!();
-    }
+/// There's no target machine in libgccjit, so this only contains what is needed to compile the
+/// modules created outside of the codegen of the crate (e.g. by LTO) like the other modules.
+pub struct TargetMachine {
+    /// The optimization level of the crate, which is used for the modules that don't have one in
+    /// their config (see set_optimization_level()).
+    opt_level: OptLevel,
+    /// The options added by add_target_options().
+    target_options: Vec<String>,
 }
 
 pub struct GccContext {
     context: Context<'static>,
+    /// Whether the object files added as driver options (by fat LTO) should be combined with
+    /// this module into a single object file.
+    should_combine_object_files: bool,
+    /// Temporary directory containing the object files to combine. It needs to live until this
+    /// module is compiled.
+    temp_dir: Option<TempDir>,
+    /// The assembly and the IR dumps of the modules combined with this module by -Zcombine-cgu,
+    /// which are appended to the ones of this module.
+    combined_outputs: Vec<(OutputType, PathBuf)>,
+    /// Whether this module is kept in memory by thin local LTO instead of being serialized to
+    /// GIMPLE bytecode (see back::lto::prepare_thin).
+    keep_in_memory_for_thin_lto: bool,
 }
 
 unsafe impl Send for GccContext {}
@@ -190,53 +243,45 @@ unsafe impl Sync for GccContext {}
 
 impl WriteBackendMethods for GccCodegenBackend {
     type Module = GccContext;
-    type TargetMachine = ();
+    type TargetMachine = TargetMachine;
     type ModuleBuffer = ModuleBuffer;
     type Context = ();
-    type ThinData = ();
+    type ThinData = ThinData;
     type ThinBuffer = ThinBuffer;
 
-    fn run_fat_lto(_cgcx: &CodegenContext<Self>, mut modules: Vec<FatLTOInput<Self>>, _cached_modules: Vec<(SerializedModule<Self::ModuleBuffer>, WorkProduct)>) -> Result<LtoModuleCodegen<Self>, FatalError> {
-        // TODO(antoyo): implement LTO by sending -flto to libgccjit and adding the appropriate gcc linker plugins.
-        // NOTE: implemented elsewhere.
-        // TODO: what is implemented elsewhere ^ ?
-        let module =
-            match modules.remove(0) {
-                FatLTOInput::InMemory(module) => module,
-                FatLTOInput::Serialized { .. } => {
-                    unimplemented!();
-                }
-            };
-        Ok(LtoModuleCodegen::Fat { module: Some(module), _serialized_bitcode: vec![] })
+    fn run_fat_lto(cgcx: &CodegenContext<Self>, modules: Vec<FatLTOInput<Self>>, cached_modules: Vec<(SerializedModule<Self::ModuleBuffer>, WorkProduct)>) -> Result<LtoModuleCodegen<Self>, FatalError> {
+        let diag_handler = cgcx.create_diag_handler();
+        back::lto::run_fat(cgcx, &diag_handler, modules, cached_modules)
     }
 
-    fn run_thin_lto(_cgcx: &CodegenContext<Self>, _modules: Vec<(String, Self::ThinBuffer)>, _cached_modules: Vec<(SerializedModule<Self::ModuleBuffer>, WorkProduct)>) -> Result<(Vec<LtoModuleCodegen<Self>>, Vec<WorkProduct>), FatalError> {
-        unimplemented!();
+    fn run_thin_lto(cgcx: &CodegenContext<Self>, modules: Vec<(String, Self::ThinBuffer)>, cached_modules: Vec<(SerializedModule<Self::ModuleBuffer>, WorkProduct)>) -> Result<(Vec<LtoModuleCodegen<Self>>, Vec<WorkProduct>), FatalError> {
+        let diag_handler = cgcx.create_diag_handler();
+        back::lto::run_thin(cgcx, &diag_handler, modules, cached_modules)
     }
 
     fn print_pass_timings(&self) {
         unimplemented!();
     }
 
-    unsafe fn optimize(_cgcx: &CodegenContext<Self>, _diag_handler: &Handler, module: &ModuleCodegen<Self::Module>, config: &ModuleConfig) -> Result<(), FatalError> {
-        module.module_llvm.context.set_optimization_level(to_gcc_opt_level(config.opt_level));
+    unsafe fn optimize(cgcx: &CodegenContext<Self>, _diag_handler: &Handler, module: &ModuleCodegen<Self::Module>, config: &ModuleConfig) -> Result<(), FatalError> {
+        set_optimization_level(cgcx, &module.name, &module.module_llvm.context, config);
         Ok(())
     }
 
-    unsafe fn optimize_thin(_cgcx: &CodegenContext<Self>, _thin: &mut ThinModule<Self>) -> Result<ModuleCodegen<Self::Module>, FatalError> {
-        unimplemented!();
+    unsafe fn optimize_thin(cgcx: &CodegenContext<Self>, thin: &mut ThinModule<Self>) -> Result<ModuleCodegen<Self::Module>, FatalError> {
+        back::lto::optimize_thin(cgcx, thin)
     }
 
     unsafe fn codegen(cgcx: &CodegenContext<Self>, diag_handler: &Handler, module: ModuleCodegen<Self::Module>, config: &ModuleConfig) -> Result<CompiledModule, FatalError> {
         back::write::codegen(cgcx, diag_handler, module, config)
     }
 
-    fn prepare_thin(_module: ModuleCodegen<Self::Module>) -> (String, Self::ThinBuffer) {
-        unimplemented!();
+    fn prepare_thin(module: ModuleCodegen<Self::Module>) -> (String, Self::ThinBuffer) {
+        back::lto::prepare_thin(module)
     }
 
-    fn serialize_module(_module: ModuleCodegen<Self::Module>) -> (String, Self::ModuleBuffer) {
-        unimplemented!();
+    fn serialize_module(module: ModuleCodegen<Self::Module>) -> (String, Self::ModuleBuffer) {
+        back::lto::serialize_module(module)
     }
 
     fn run_lto_pass_manager(_cgcx: &CodegenContext<Self>, _module: &ModuleCodegen<Self::Module>, _config: &ModuleConfig, _thin: bool) -> Result<(), FatalError> {
@@ -257,35 +302,253 @@ pub fn __rustc_codegen_backend() -> Box<dyn CodegenBackend> {
     })
 }
 
-fn to_gcc_opt_level(optlevel: Option<OptLevel>) -> OptimizationLevel {
+fn to_gcc_opt_level(optlevel: OptLevel) -> OptimizationLevel {
     match optlevel {
-        None => OptimizationLevel::None,
-        Some(level) => {
-            match level {
-                OptLevel::No => OptimizationLevel::None,
-                OptLevel::Less => OptimizationLevel::Limited,
-                OptLevel::Default => OptimizationLevel::Standard,
-                OptLevel::Aggressive => OptimizationLevel::Aggressive,
-                OptLevel::Size | OptLevel::SizeMin => OptimizationLevel::Limited,
-            }
+        OptLevel::No => OptimizationLevel::None,
+        OptLevel::Less => OptimizationLevel::Limited,
+        OptLevel::Default => OptimizationLevel::Standard,
+        OptLevel::Aggressive => OptimizationLevel::Aggressive,
+        // NOTE: overridden by the -Os and -Oz options in set_optimization_level().
+        OptLevel::Size | OptLevel::SizeMin => OptimizationLevel::Standard,
+    }
+}
+
+/// Set the optimization level of a module.
+///
+/// Only the regular modules have an optimization level in their config, so the other modules
+/// (like the allocator module) use the optimization level of the crate returned by the target
+/// machine factory.
+fn set_optimization_level(cgcx: &CodegenContext<GccCodegenBackend>, module_name: &str, context: &Context<'_>, config: &ModuleConfig) {
+    let optlevel = config.opt_level.unwrap_or_else(|| {
+        let config = TargetMachineFactoryConfig::new(cgcx, module_name);
+        (cgcx.tm_factory)(config).expect("target machine").opt_level
+    });
+    context.set_optimization_level(to_gcc_opt_level(optlevel));
+    // NOTE: libgccjit doesn't have optimization levels for the code size, so use the command-line
+    // options, which take precedence over the -O option added by set_optimization_level().
+    match optlevel {
+        OptLevel::Size => context.add_command_line_option("-Os"),
+        OptLevel::SizeMin => context.add_command_line_option("-Oz"),
+        _ => (),
+    }
+}
+
+fn needs_gcov_runtime(sess: &Session) -> bool {
+    sess.instrument_coverage() || sess.opts.cg.profile_generate.enabled()
+}
+
+/// The gcc driver used to query the target options: the linker when it is gcc, so that the gcc
+/// of the target is used when cross-compiling.
+///
+/// NOTE: libgccjit doesn't provide a way to query the target options, and -march=native is
+/// handled by the gcc driver, not by the compiler.
+fn gcc_driver(sess: &Session) -> PathBuf {
+    match linker_and_flavor(sess) {
+        (linker, LinkerFlavor::Gcc) => linker,
+        _ => PathBuf::from("gcc"),
+    }
+}
+
+/// Run the gcc driver with the target `options` and the `arguments`, returning what it prints on
+/// the standard output and the standard error, or None (after a warning) if it fails.
+fn run_gcc_driver(sess: &Session, options: &[String], arguments: &[&str]) -> Option<(String, String)> {
+    let driver = gcc_driver(sess);
+    let output = Command::new(&driver).args(options).args(arguments).stdin(Stdio::null()).output();
+    match output {
+        Ok(output) if output.status.success() =>
+            Some((String::from_utf8_lossy(&output.stdout).into_owned(), String::from_utf8_lossy(&output.stderr).into_owned())),
+        Ok(output) => {
+            sess.warn(&format!("`{}` failed to print the target options: {}", driver.display(), String::from_utf8_lossy(&output.stderr)));
+            None
+        },
+        Err(error) => {
+            sess.warn(&format!("failed to run `{}` to get the target options: {}", driver.display(), error));
+            None
         },
     }
 }
 
-fn handle_native(name: &str) -> &str {
+/// Run `gcc -Q --help=target` with the target `options` to get the target options that gcc
+/// enables for them and the CPUs it supports.
+fn gcc_target_help(sess: &Session, options: &[String]) -> Option<String> {
+    run_gcc_driver(sess, options, &["-Q", "--help=target"]).map(|(stdout, _)| stdout)
+}
+
+/// The options that the gcc driver passes to the compiler for -march=native and -mtune=native:
+/// the CPU of the host and its features, since some features of this CPU might not be available
+/// (e.g. AVX in some virtual machines), and the sizes of its caches.
+fn native_options(sess: &Session) -> &'static [String] {
+    static NATIVE_OPTIONS: SyncOnceCell<Vec<String>> = SyncOnceCell::new();
+    NATIVE_OPTIONS.get_or_init(|| {
+        // NOTE: -### prints the commands run by the driver on the standard error.
+        let arguments = ["-###", "-march=native", "-mtune=native", "-E", "-x", "c", "-"];
+        let stderr =
+            match run_gcc_driver(sess, &[], &arguments) {
+                Some((_, stderr)) => stderr,
+                None => return vec![],
+            };
+        let mut options = vec![];
+        let mut arguments = stderr.lines()
+            .find(|line| line.contains("-march="))
+            .unwrap_or("")
+            .split_whitespace()
+            .map(|argument| argument.trim_matches('"'))
+            .skip_while(|argument| !argument.starts_with("-march="));
+        while let Some(argument) = arguments.next() {
+            if argument == "--param" {
+                if let Some(param) = arguments.next() {
+                    options.push(format!("--param={}", param));
+                }
+            }
+            else if argument.starts_with("-m") {
+                options.push(argument.to_string());
+            }
+            if argument.starts_with("-mtune=") {
+                break;
+            }
+        }
+        if options.is_empty() {
+            sess.warn("cannot find the CPU of the host in the output of gcc: code will be generated for the default CPU");
+        }
+        options
+    })
+}
+
+/// Get the value of the `option` (e.g. `-march=`) passed by the gcc driver for the host CPU.
+fn native_option_value(sess: &Session, option: &str) -> Option<&'static str> {
+    native_options(sess).iter()
+        .find_map(|native_option| native_option.strip_prefix(option))
+}
+
+pub fn target_cpu(sess: &Session) -> &str {
+    let name = sess.opts.cg.target_cpu.as_ref().unwrap_or(&sess.target.cpu);
     if name != "native" {
         return name;
     }
+    native_option_value(sess, "-march=").unwrap_or(&sess.target.cpu)
+}
 
-    unimplemented!();
+pub fn tune_cpu(sess: &Session) -> Option<&str> {
+    let name = sess.opts.debugging_opts.tune_cpu.as_ref()?;
+    if name != "native" {
+        return Some(name);
+    }
+    native_option_value(sess, "-mtune=")
 }
 
-pub fn target_cpu(sess: &Session) -> &str {
-    let name = sess.opts.cg.target_cpu.as_ref().unwrap_or(&sess.target.cpu);
-    handle_native(name)
+/// Get the name of the gcc option (without the `-m` prefix) enabling the Rust target `feature`.
+///
+/// NOTE: gcc uses -m<feature> options for the features only on some architectures (e.g. the
+/// features are part of -march on ARM and RISC-V), and some Rust features have no equivalent in
+/// gcc, since they only enable a cfg(target_feature).
+fn to_gcc_feature<'a>(sess: &Session, feature: &'a str) -> Option<&'a str> {
+    match (&*sess.target.arch, feature) {
+        ("x86" | "x86_64", "avx512gfni") => Some("gfni"),
+        ("x86" | "x86_64", "avx512vaes") => Some("vaes"),
+        ("x86" | "x86_64", "avx512vpclmulqdq") => Some("vpclmulqdq"),
+        ("x86" | "x86_64", "bmi1") => Some("bmi"),
+        ("x86" | "x86_64", "cmpxchg16b") => Some("cx16"),
+        ("x86" | "x86_64", "ermsb") => None,
+        ("x86" | "x86_64", "pclmulqdq") => Some("pclmul"),
+        ("x86" | "x86_64", "rdrand") => Some("rdrnd"),
+        ("x86" | "x86_64", feature) => Some(feature),
+        ("powerpc" | "powerpc64", "power8-altivec" | "power9-altivec") => None,
+        ("powerpc" | "powerpc64", feature) => Some(feature),
+        ("mips" | "mips64", feature) => Some(feature),
+        _ => None,
+    }
+}
+
+/// The options to generate code for the CPU and the features requested by `-Ctarget-cpu`,
+/// `-Ztune-cpu` and `-Ctarget-feature`.
+///
+/// NOTE: the options are computed once since they are added to every context, so that the
+/// warnings are only emitted once.
+fn target_options(sess: &Session) -> &'static [String] {
+    static TARGET_OPTIONS: SyncOnceCell<Vec<String>> = SyncOnceCell::new();
+    TARGET_OPTIONS.get_or_init(|| {
+        let mut options = vec![];
+        // NOTE: the default CPU of the target is not passed since its name is not necessarily
+        // one that gcc knows: gcc already generates code for its default CPU in that case.
+        match sess.opts.cg.target_cpu.as_deref() {
+            Some("native") => options.extend(native_options(sess).iter().filter(|option| !option.starts_with("-mtune=")).cloned()),
+            Some(_) => options.push(format!("-march={}", target_cpu(sess))),
+            None => (),
+        }
+        if let Some(tune_cpu) = tune_cpu(sess) {
+            options.push(format!("-mtune={}", tune_cpu));
+        }
+
+        let supported_features = supported_target_features(sess);
+        for feature in sess.opts.cg.target_feature.split(',').filter(|feature| !feature.is_empty()) {
+            let (enable, name) =
+                match (feature.strip_prefix('+'), feature.strip_prefix('-')) {
+                    (Some(name), _) => (true, name),
+                    (_, Some(name)) => (false, name),
+                    _ => {
+                        sess.warn(&format!("target feature `{}` must begin with a `+` to enable or `-` to disable it", feature));
+                        continue;
+                    },
+                };
+            if !supported_features.iter().any(|&(supported_feature, _)| supported_feature == name) {
+                sess.warn(&format!("unknown feature specified for `-Ctarget-feature`: `{}`", name));
+                continue;
+            }
+            match to_gcc_feature(sess, name) {
+                Some(gcc_feature) => {
+                    let prefix = if enable { "-m" } else { "-mno-" };
+                    options.push(format!("{}{}", prefix, gcc_feature));
+                },
+                None => sess.warn(&format!("the target feature `{}` has no equivalent in gcc: it is ignored", name)),
+            }
+        }
+        options
+    })
+}
+
+/// Add the options to generate code for the target requested by the command-line options of
+/// rustc.
+fn add_target_options(context: &Context<'_>, sess: &Session) {
+    for option in target_options(sess) {
+        context.add_command_line_option(option);
+    }
+}
+
+fn print_target_cpus(sess: &Session) {
+    println!("Available CPUs for this target:");
+    if let Some(native_cpu) = native_option_value(sess, "-march=") {
+        println!("    native - Select the CPU of the current host (currently {}).", native_cpu);
+    }
+    let help = gcc_target_help(sess, &[]).unwrap_or_default();
+    let cpus = help.lines()
+        .skip_while(|line| line.trim() != "Known valid arguments for -march= option:")
+        .nth(1)
+        .unwrap_or("");
+    for cpu in cpus.split_whitespace().filter(|&cpu| cpu != "native") {
+        println!("    {}", cpu);
+    }
 }
 
 pub fn target_features(sess: &Session) -> Vec<Symbol> {
+    let options = target_options(sess);
+    let enabled_options: FxHashSet<String> =
+        match gcc_target_help(sess, options) {
+            Some(help) =>
+                help.lines()
+                    .filter_map(|line| {
+                        let mut words = line.split_whitespace();
+                        match (words.next(), words.next()) {
+                            (Some(option), Some("[enabled]")) => Some(option.to_string()),
+                            _ => None,
+                        }
+                    })
+                    .collect(),
+            None => {
+                sess.warn("only the target features enabled by `-Ctarget-feature` will be available in `cfg(target_feature)`");
+                options.iter().cloned().collect()
+            },
+        };
+
     supported_target_features(sess)
         .iter()
         .filter_map(
@@ -293,9 +556,11 @@ pub fn target_features(sess: &Session) -> Vec<Symbol> {
                 if sess.is_nightly_build() || gate.is_none() { Some(feature) } else { None }
             },
         )
-        .filter(|_feature| {
-            // TODO(antoyo): implement a way to get enabled feature in libgccjit.
-            false
+        .filter(|feature| {
+            match to_gcc_feature(sess, feature) {
+                Some(gcc_feature) => enabled_options.contains(&format!("-m{}", gcc_feature)),
+                None => false,
+            }
         })
         .map(|feature| Symbol::intern(feature))
         .collect()
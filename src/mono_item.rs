@@ -1,38 +1,69 @@
+use gccjit::{FnAttribute, GlobalKind, VarAttribute};
 use rustc_codegen_ssa::traits::PreDefineMethods;
 use rustc_middle::middle::codegen_fn_attrs::CodegenFnAttrFlags;
 use rustc_middle::mir::mono::{Linkage, Visibility};
 use rustc_middle::ty::{self, Instance, TypeFoldable};
 use rustc_middle::ty::layout::{FnAbiOf, LayoutOf};
-use rustc_span::def_id::DefId;
+use rustc_span::def_id::{DefId, LOCAL_CRATE};
 
-use crate::base;
+use crate::{attributes, base};
 use crate::context::CodegenCx;
 use crate::type_of::LayoutGccExt;
 
 impl<'gcc, 'tcx> PreDefineMethods<'tcx> for CodegenCx<'gcc, 'tcx> {
-    fn predefine_static(&self, def_id: DefId, _linkage: Linkage, _visibility: Visibility, symbol_name: &str) {
+    fn predefine_static(&self, def_id: DefId, linkage: Linkage, visibility: Visibility, symbol_name: &str) {
         let attrs = self.tcx.codegen_fn_attrs(def_id);
         let instance = Instance::mono(self.tcx, def_id);
         let ty = instance.ty(self.tcx, ty::ParamEnv::reveal_all());
         let gcc_type = self.layout_of(ty).gcc_type(self, true);
 
         let is_tls = attrs.flags.contains(CodegenFnAttrFlags::THREAD_LOCAL);
-        let global = self.define_global(symbol_name, gcc_type, is_tls, attrs.link_section);
+        let is_internal = linkage == Linkage::Internal || linkage == Linkage::Private;
+        let global =
+            if is_internal {
+                self.declare_global(symbol_name, gcc_type, GlobalKind::Internal, is_tls, attrs.link_section)
+            }
+            else {
+                self.define_global(symbol_name, gcc_type, is_tls, attrs.link_section)
+            };
+        if base::is_weak_linkage(linkage) {
+            global.add_attribute(VarAttribute::Weak);
+        }
 
-        // TODO(antoyo): set linkage and visibility.
+        if !is_internal {
+            global.add_attribute(VarAttribute::Visibility(base::visibility_to_gcc(visibility)));
+        }
         self.instances.borrow_mut().insert(instance, global);
     }
 
-    fn predefine_fn(&self, instance: Instance<'tcx>, linkage: Linkage, _visibility: Visibility, symbol_name: &str) {
+    fn predefine_fn(&self, instance: Instance<'tcx>, linkage: Linkage, visibility: Visibility, symbol_name: &str) {
         assert!(!instance.substs.needs_infer());
 
         let fn_abi = self.fn_abi_of_instance(instance, ty::List::empty());
         self.linkage.set(base::linkage_to_gcc(linkage));
-        let _decl = self.declare_fn(symbol_name, &fn_abi);
-        //let attrs = self.tcx.codegen_fn_attrs(instance.def_id());
+        let decl = self.rvalue_as_function(self.declare_fn(symbol_name, &fn_abi));
+        attributes::from_fn_attrs(self, decl, instance);
+        if base::is_weak_linkage(linkage) {
+            decl.add_attribute(FnAttribute::Weak);
+        }
 
-        // TODO(antoyo): call set_link_section() to allow initializing argc/argv.
-        // TODO(antoyo): set unique comdat.
-        // TODO(antoyo): use inline attribute from there in linkage.set() above.
+        // If we're compiling the compiler-builtins crate, e.g., the equivalent of
+        // compiler-rt, then we want to implicitly compile everything with hidden
+        // visibility as we're going to link this object all over the place but
+        // don't want the symbols to get exported.
+        if linkage != Linkage::Internal && linkage != Linkage::Private {
+            let visibility =
+                if self.tcx.is_compiler_builtins(LOCAL_CRATE) {
+                    gccjit::Visibility::Hidden
+                }
+                else {
+                    base::visibility_to_gcc(visibility)
+                };
+            decl.add_attribute(FnAttribute::Visibility(visibility));
+        }
+
+        // NOTE: the #[inline] functions don't need a special linkage to be inlined across codegen
+        // units since the partitioning already puts an internal copy of them in every codegen unit
+        // that uses them when optimizing.
     }
 }
@@ -4,8 +4,9 @@ use gccjit::{RValue, Struct, Type};
 use rustc_codegen_ssa::traits::{BaseTypeMethods, DerivedTypeMethods};
 use rustc_codegen_ssa::common::TypeKind;
 use rustc_middle::bug;
+use rustc_middle::ty;
 use rustc_middle::ty::layout::TyAndLayout;
-use rustc_target::abi::{AddressSpace, Align, Integer, Size};
+use rustc_target::abi::{AddressSpace, Align, Integer, Size, Variants};
 
 use crate::context::CodegenCx;
 use crate::type_of::LayoutGccExt;
@@ -109,7 +110,7 @@ impl<'gcc, 'tcx> BaseTypeMethods<'tcx> for CodegenCx<'gcc, 'tcx> {
             return typ.clone();
         }
         let fields: Vec<_> = fields.iter().enumerate()
-            .map(|(index, field)| self.context.new_field(None, *field, &format!("field{}_TODO", index)))
+            .map(|(index, field)| self.context.new_field(None, *field, &format!("__{}", index)))
             .collect();
         // TODO(antoyo): use packed.
         let typ = self.context.new_struct_type(None, "struct", &fields).as_type();
@@ -197,10 +198,11 @@ impl<'gcc, 'tcx> CodegenCx<'gcc, 'tcx> {
         self.type_array(self.type_from_integer(unit), size / unit_size)
     }
 
-    pub fn set_struct_body(&self, typ: Struct<'gcc>, fields: &[Type<'gcc>], _packed: bool) {
+    pub fn set_struct_body(&self, typ: Struct<'gcc>, fields: &[Type<'gcc>], field_names: &[String], _packed: bool) {
+        assert_eq!(fields.len(), field_names.len());
         // TODO(antoyo): use packed.
-        let fields: Vec<_> = fields.iter().enumerate()
-            .map(|(index, field)| self.context.new_field(None, *field, &format!("field_{}", index)))
+        let fields: Vec<_> = fields.iter().zip(field_names)
+            .map(|(field, name)| self.context.new_field(None, *field, name))
             .collect();
         typ.set_fields(None, &fields);
     }
@@ -268,3 +270,57 @@ pub fn struct_fields<'gcc, 'tcx>(cx: &CodegenCx<'gcc, 'tcx>, layout: TyAndLayout
 
     (result, packed)
 }
+
+/// Get the names of the fields returned by `struct_fields`, so that the debugger shows the Rust
+/// field names.
+pub fn struct_field_names(layout: TyAndLayout<'_>) -> Vec<String> {
+    let field_count = layout.fields.count();
+    let field_names: Vec<_> = (0..field_count).map(|index| field_name(layout, index)).collect();
+
+    // NOTE: the names of the padding fields must not be the same as the name of a Rust field.
+    let padding_name = |name: String| {
+        let mut name = name;
+        while field_names.contains(&name) {
+            name.insert(0, '_');
+        }
+        name
+    };
+
+    let mut result = Vec::with_capacity(1 + field_count * 2);
+    for i in layout.fields.index_by_increasing_offset() {
+        result.push(padding_name(format!("__padding{}", i)));
+        result.push(field_names[i].clone());
+    }
+    if !layout.is_unsized() && field_count > 0 {
+        result.push(padding_name("__padding_end".to_string()));
+    }
+
+    result
+}
+
+fn field_name(layout: TyAndLayout<'_>, index: usize) -> String {
+    let field =
+        match *layout.ty.kind() {
+            ty::Adt(def, _) if !def.is_enum() => &def.non_enum_variant().fields[index],
+            ty::Adt(def, _) =>
+                match layout.variants {
+                    Variants::Single { index: variant_index } => &def.variants[variant_index].fields[index],
+                    // NOTE: the only field of an enum with multiple variants is its tag. The
+                    // variants are not described in the debug info: their fields are not part of
+                    // the gcc type of the enum, which is accessed by field index by the codegen,
+                    // and they can overlap the tag, so they cannot be added to this type.
+                    Variants::Multiple { .. } => return "tag".to_string(),
+                },
+            // NOTE: use the same names as the LLVM debug info for tuples, closures and generators.
+            _ => return format!("__{}", index),
+        };
+    let name = field.name.as_str();
+    // NOTE: the fields of the tuple structs and variants are named like the fields of the tuples,
+    // since a name like 0 is not a valid identifier.
+    if name.starts_with(|char: char| char.is_ascii_digit()) {
+        format!("__{}", name)
+    }
+    else {
+        name.to_string()
+    }
+}
@@ -3,15 +3,16 @@ use std::fmt::Write;
 use gccjit::{Struct, Type};
 use crate::rustc_codegen_ssa::traits::{BaseTypeMethods, DerivedTypeMethods, LayoutTypeMethods};
 use rustc_middle::bug;
-use rustc_middle::ty::{self, Ty, TypeFoldable};
+use rustc_middle::ty::{self, ParamEnv, Ty, TypeFoldable};
 use rustc_middle::ty::layout::{FnAbiOf, LayoutOf, TyAndLayout};
 use rustc_middle::ty::print::with_no_trimmed_paths;
+use rustc_session::config::DebugInfo;
 use rustc_target::abi::{self, Abi, F32, F64, FieldsShape, Int, Integer, Pointer, PointeeInfo, Size, TyAbiInterface, Variants};
 use rustc_target::abi::call::{CastTarget, FnAbi, Reg};
 
 use crate::abi::{FnAbiGccExt, GccType};
 use crate::context::CodegenCx;
-use crate::type_::struct_fields;
+use crate::type_::{struct_field_names, struct_fields};
 
 impl<'gcc, 'tcx> CodegenCx<'gcc, 'tcx> {
     fn type_from_unsigned_integer(&self, i: Integer) -> Type<'gcc> {
@@ -34,13 +35,22 @@ pub fn uncached_gcc_type<'gcc, 'tcx>(cx: &CodegenCx<'gcc, 'tcx>, layout: TyAndLa
             return cx.context.new_vector_type(element, count);
         },
         Abi::ScalarPair(..) => {
-            return cx.type_struct(
-                &[
-                    layout.scalar_pair_element_gcc_type(cx, 0, false),
-                    layout.scalar_pair_element_gcc_type(cx, 1, false),
-                ],
-                false,
-            );
+            let fields = [
+                layout.scalar_pair_element_gcc_type(cx, 0, false),
+                layout.scalar_pair_element_gcc_type(cx, 1, false),
+            ];
+            // NOTE: the struct types are cached by the types of their fields, so the same type can
+            // be used by other scalar pairs. As such, the fat pointers get their own named struct
+            // type when the names are needed, so that the debugger shows their fields.
+            if !cx.sess().fewer_names() || cx.sess().opts.debuginfo != DebugInfo::None {
+                if let Some(field_names) = fat_pointer_field_names(cx, layout.ty) {
+                    let name = with_no_trimmed_paths(|| layout.ty.to_string());
+                    let gcc_type = cx.type_named_struct(&name);
+                    cx.set_struct_body(gcc_type, &fields, &field_names, false);
+                    return gcc_type.as_type();
+                }
+            }
+            return cx.type_struct(&fields, false);
         }
         Abi::Uninhabited | Abi::Aggregate { .. } => {}
     }
@@ -50,7 +60,7 @@ pub fn uncached_gcc_type<'gcc, 'tcx>(cx: &CodegenCx<'gcc, 'tcx>, layout: TyAndLa
         // in problematically distinct types due to HRTB and subtyping (see #47638).
         // ty::Dynamic(..) |
         ty::Adt(..) | ty::Closure(..) | ty::Foreign(..) | ty::Generator(..) | ty::Str
-            if !cx.sess().fewer_names() =>
+            if !cx.sess().fewer_names() || cx.sess().opts.debuginfo != DebugInfo::None =>
         {
             let mut name = with_no_trimmed_paths(|| layout.ty.to_string());
             if let (&ty::Adt(def, _), &Variants::Single { index }) =
@@ -85,7 +95,7 @@ pub fn uncached_gcc_type<'gcc, 'tcx>(cx: &CodegenCx<'gcc, 'tcx>, layout: TyAndLa
                 None => cx.type_struct(&[fill], packed),
                 Some(ref name) => {
                     let gcc_type = cx.type_named_struct(name);
-                    cx.set_struct_body(gcc_type, &[fill], packed);
+                    cx.set_struct_body(gcc_type, &[fill], &["fill".to_string()], packed);
                     gcc_type.as_type()
                 },
             }
@@ -106,6 +116,18 @@ pub fn uncached_gcc_type<'gcc, 'tcx>(cx: &CodegenCx<'gcc, 'tcx>, layout: TyAndLa
     }
 }
 
+/// Get the names of the fields of a fat pointer, which are the same as in the LLVM debug info.
+fn fat_pointer_field_names<'tcx>(cx: &CodegenCx<'_, 'tcx>, ty: Ty<'tcx>) -> Option<Vec<String>> {
+    let pointee = ty.builtin_deref(true)?.ty;
+    let field_names =
+        match cx.tcx.struct_tail_erasing_lifetimes(pointee, ParamEnv::reveal_all()).kind() {
+            ty::Slice(_) | ty::Str => ["data_ptr", "length"],
+            ty::Dynamic(..) => ["pointer", "vtable"],
+            _ => return None,
+        };
+    Some(field_names.iter().map(|name| name.to_string()).collect())
+}
+
 pub trait LayoutGccExt<'tcx> {
     fn is_gcc_immediate(&self) -> bool;
     fn is_gcc_scalar_pair(&self) -> bool;
@@ -180,7 +202,7 @@ impl<'tcx> LayoutGccExt<'tcx> for TyAndLayout<'tcx> {
                 // Since we might be trying to generate a type containing another type which is not
                 // completely generated yet, we deferred setting the fields until now.
                 let (fields, packed) = struct_fields(cx, layout);
-                cx.set_struct_body(struct_type, &fields, packed);
+                cx.set_struct_body(struct_type, &fields, &struct_field_names(layout), packed);
             }
             return ty;
         }
@@ -208,7 +230,7 @@ impl<'tcx> LayoutGccExt<'tcx> for TyAndLayout<'tcx> {
 
         if let Some((ty, layout)) = defer {
             let (fields, packed) = struct_fields(cx, layout);
-            cx.set_struct_body(ty, &fields, packed);
+            cx.set_struct_body(ty, &fields, &struct_field_names(layout), packed);
         }
 
         ty
@@ -60,6 +60,11 @@ function build_sysroot() {
     time ./build_sysroot/build_sysroot.sh
 }
 
+function build_sysroot_release() {
+    echo "[BUILD] sysroot in release mode"
+    time ./build_sysroot/build_sysroot.sh --release
+}
+
 function std_tests() {
     echo "[AOT] arbitrary_self_types_pointers_and_wrappers"
     $RUSTC example/arbitrary_self_types_pointers_and_wrappers.rs --crate-name arbitrary_self_types_pointers_and_wrappers --crate-type bin --target $TARGET_TRIPLE
@@ -93,10 +98,6 @@ function std_tests() {
     $RUSTC example/mod_bench.rs --crate-type bin --target $TARGET_TRIPLE
 }
 
-# FIXME(antoyo): linker gives multiple definitions error on Linux
-#echo "[BUILD] sysroot in release mode"
-#./build_sysroot/build_sysroot.sh --release
-
 # TODO(antoyo): uncomment when it works.
 #pushd simple-raytracer
 #if [[ "$HOST_TRIPLE" = "$TARGET_TRIPLE" ]]; then
@@ -226,6 +227,11 @@ case $1 in
         build_sysroot
         ;;
 
+    "--test-release-sysroot")
+        build_sysroot_release
+        std_tests
+        ;;
+
     *)
         clean
         mini_tests
@@ -0,0 +1,11 @@
+// Compile-flags: -Copt-level=2
+// CHECK: .text.unlikely
+
+#![no_std]
+
+#[cold]
+#[inline(never)]
+#[no_mangle]
+pub fn cold_path(value: u32) -> u32 {
+    value.wrapping_mul(3)
+}
@@ -0,0 +1,23 @@
+// Compile-flags: -Copt-level=0
+// CHECK-NOT: call always_inlined
+// CHECK: call hinted
+
+#![no_std]
+
+#[inline(always)]
+#[no_mangle]
+fn always_inlined(value: u32) -> u32 {
+    value.wrapping_mul(3)
+}
+
+// Without optimizations, gcc only inlines the always_inline functions.
+#[inline]
+#[no_mangle]
+fn hinted(value: u32) -> u32 {
+    value.wrapping_add(5)
+}
+
+#[no_mangle]
+pub fn caller(value: u32) -> u32 {
+    hinted(always_inlined(value))
+}
@@ -0,0 +1,15 @@
+// Compile-flags: -Copt-level=2
+// CHECK: call never_inlined
+
+#![no_std]
+
+#[inline(never)]
+#[no_mangle]
+pub fn never_inlined(value: u32) -> u32 {
+    value.wrapping_mul(3)
+}
+
+#[no_mangle]
+pub fn caller(value: u32) -> u32 {
+    never_inlined(value).wrapping_add(1)
+}
@@ -0,0 +1,15 @@
+// Compile-flags: -Copt-level=0
+// CHECK: .section .text.custom_section,"ax"
+// CHECK: .section .data.custom_section,"aw"
+
+#![no_std]
+
+#[link_section = ".text.custom_section"]
+#[no_mangle]
+pub fn function_in_custom_section() -> u32 {
+    42
+}
+
+#[link_section = ".data.custom_section"]
+#[no_mangle]
+pub static mut STATIC_IN_CUSTOM_SECTION: u32 = 42;
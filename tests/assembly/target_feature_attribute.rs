@@ -0,0 +1,23 @@
+// Compile-flags: -Copt-level=3
+// CHECK: vpaddd
+// CHECK: ermsb_function:
+
+#![feature(ermsb_target_feature)]
+#![no_std]
+
+// AVX2 is not part of the baseline of x86-64, so the addition is only vectorized with vpaddd if
+// the target attribute is applied to the function.
+#[target_feature(enable = "avx2")]
+#[no_mangle]
+pub unsafe fn add_arrays(a: &[u32; 8], b: &[u32; 8], result: &mut [u32; 8]) {
+    for i in 0..8 {
+        result[i] = a[i].wrapping_add(b[i]);
+    }
+}
+
+// ermsb has no equivalent in gcc, so it must not be part of the target attribute.
+#[target_feature(enable = "ermsb")]
+#[no_mangle]
+pub unsafe fn ermsb_function() -> u32 {
+    42
+}
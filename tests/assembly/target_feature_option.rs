@@ -0,0 +1,34 @@
+// Compile-flags: -Copt-level=3 -Ctarget-feature=+avx2,+bmi1,+ermsb
+// CHECK: avx2_enabled:
+// CHECK: bmi1_enabled:
+// CHECK: vpaddd
+// CHECK: blsr
+
+#![no_std]
+
+// These features are not part of the baseline of x86-64, so these functions only exist if the
+// features are enabled.
+#[cfg(target_feature = "avx2")]
+#[no_mangle]
+pub fn avx2_enabled() -> u32 {
+    42
+}
+
+// bmi1 is called bmi in gcc.
+#[cfg(target_feature = "bmi1")]
+#[no_mangle]
+pub fn bmi1_enabled() -> u32 {
+    42
+}
+
+#[no_mangle]
+pub fn add_arrays(a: &[u32; 8], b: &[u32; 8], result: &mut [u32; 8]) {
+    for i in 0..8 {
+        result[i] = a[i].wrapping_add(b[i]);
+    }
+}
+
+#[no_mangle]
+pub fn reset_lowest_set_bit(value: u32) -> u32 {
+    value & value.wrapping_sub(1)
+}
@@ -0,0 +1,12 @@
+// Compile-flags: -Copt-level=3
+// CHECK: 13USED_REGISTRY
+// CHECK-NOT: 15UNUSED_REGISTRY
+
+#![no_std]
+
+// The #[used] statics must be kept even if they are not referenced.
+#[used]
+static USED_REGISTRY: [u32; 3] = [1, 2, 3];
+
+#[allow(dead_code)]
+static UNUSED_REGISTRY: [u32; 3] = [4, 5, 6];
@@ -0,0 +1,23 @@
+// Compile-flags: -Copt-level=0
+// CHECK: .globl default_function
+// CHECK-NOT: .hidden default_function
+// CHECK-NOT: .protected default_function
+// CHECK: .globl DEFAULT_STATIC
+// CHECK-NOT: .hidden DEFAULT_STATIC
+// CHECK-NOT: .protected DEFAULT_STATIC
+// CHECK-NOT: .globl _ZN10visibility15INTERNAL_STATIC
+// CHECK-NOT: .hidden _ZN10visibility15INTERNAL_STATIC
+
+#![no_std]
+
+// The exported symbols have the default visibility on this target.
+#[no_mangle]
+pub fn default_function() -> *const u32 {
+    &INTERNAL_STATIC
+}
+
+#[no_mangle]
+pub static DEFAULT_STATIC: u32 = 42;
+
+// The internal statics are local symbols, so they cannot clash with the symbols of other objects.
+static INTERNAL_STATIC: u32 = 42;
@@ -0,0 +1,22 @@
+#![no_std]
+
+// The functions are in different modules so that they are put in different codegen units, which
+// must all be in the combined object file.
+#[no_mangle]
+pub fn root_function() -> u32 {
+    1
+}
+
+pub mod first {
+    #[no_mangle]
+    pub fn first_function() -> u32 {
+        2
+    }
+}
+
+pub mod second {
+    #[no_mangle]
+    pub fn second_function() -> u32 {
+        3
+    }
+}
@@ -0,0 +1,33 @@
+struct Point {
+    x: u32,
+    y: u32,
+}
+
+#[inline(never)]
+fn breakpoint() {
+}
+
+#[allow(dead_code)]
+enum Shape {
+    Circle(u32),
+    Square(u32),
+}
+
+fn main() {
+    let number = 42u32;
+    let point = Point { x: 1, y: 2 };
+    let numbers = [1u32, 2, 3];
+    let slice = &numbers[..];
+    let shape = Shape::Square(3);
+    let captured = 7u32;
+    let closure = || {
+        breakpoint();
+        captured + number
+    };
+    breakpoint();
+    let side = match shape {
+        Shape::Circle(radius) => radius,
+        Shape::Square(side) => side,
+    };
+    assert_eq!(closure() + point.x + point.y + slice.len() as u32 + side, 58);
+}
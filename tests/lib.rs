@@ -1,7 +1,10 @@
 use std::{
     env::{self, current_dir},
-    path::PathBuf,
+    fs,
+    path::{Path, PathBuf},
     process::Command,
+    sync::Arc,
+    thread,
 };
 
 use lang_tester::LangTester;
@@ -14,6 +17,39 @@ fn main() {
     let gcc_path = include_str!("../gcc_path");
     let gcc_path = gcc_path.trim();
     env::set_var("LD_LIBRARY_PATH", gcc_path);
+    let panic_strategy = env::var("CG_GCCJIT_PANIC_STRATEGY").unwrap_or_else(|_| "abort".to_string());
+    let rustc = Arc::new(Rustc { current_dir, panic_strategy });
+
+    // NOTE: the tests compile and run their programs in their own temporary directories, so they
+    // can run in parallel.
+    let tests: &[fn(&Rustc)] = &[
+        test_code_size,
+        test_assembly,
+        test_combine_cgu,
+        test_mixed_backends,
+        test_coverage,
+        test_profile_guided_optimization,
+        test_debuginfo,
+        test_split_debuginfo,
+        test_lto,
+        test_weak_linkage,
+        test_used,
+        test_linker_plugin_lto,
+        test_unwind,
+    ];
+    let threads: Vec<_> = tests.iter()
+        .map(|&test| {
+            let rustc = rustc.clone();
+            thread::spawn(move || test(&rustc))
+        })
+        .collect();
+    // NOTE: the panic message of a failed test is printed by its thread.
+    let failures = threads.into_iter()
+        .map(|thread| thread.join())
+        .filter(Result::is_err)
+        .count();
+    assert_eq!(failures, 0, "{} test(s) failed", failures);
+
     LangTester::new()
         .test_dir("tests/run")
         .test_file_filter(|path| path.extension().expect("extension").to_str().expect("to_str") == "rs")
@@ -32,12 +68,8 @@ fn main() {
             let mut exe = PathBuf::new();
             exe.push(&tempdir);
             exe.push(path.file_stem().expect("file_stem"));
-            let mut compiler = Command::new("rustc");
+            let mut compiler = rustc.command();
             compiler.args(&[
-                &format!("-Zcodegen-backend={}/target/debug/librustc_codegen_gcc.so", current_dir),
-                "--sysroot", &format!("{}/build_sysroot/sysroot/", current_dir),
-                "-Zno-parallel-llvm",
-                "-C", "panic=abort",
                 "-C", "link-arg=-lc",
                 "-o", exe.to_str().expect("to_str"),
                 path.to_str().expect("to_str"),
@@ -48,3 +80,509 @@ fn main() {
         })
         .run();
 }
+
+/// Options shared by the rustc invocations of the tests.
+struct Rustc {
+    current_dir: String,
+    panic_strategy: String,
+}
+
+impl Rustc {
+    /// Create a rustc command that uses this backend.
+    fn command(&self) -> Command {
+        let mut command = self.llvm_command();
+        command.arg(format!("-Zcodegen-backend={}/target/debug/librustc_codegen_gcc.so", self.current_dir));
+        command
+    }
+
+    /// Create a rustc command that uses the LLVM backend with the sysroot of this backend.
+    fn llvm_command(&self) -> Command {
+        let mut command = Command::new("rustc");
+        command.args(&[
+            "--sysroot", &format!("{}/build_sysroot/sysroot/", self.current_dir),
+            "-Zno-parallel-llvm",
+            "-C", &format!("panic={}", self.panic_strategy),
+        ]);
+        command
+    }
+}
+
+/// Check that optimizing for size (-Copt-level=s and z) produces smaller code than -Copt-level=3.
+fn test_code_size(rustc: &Rustc) {
+    let tempdir = TempDir::new().expect("temp dir");
+    let object_size = |opt_level: &str| {
+        let mut object = PathBuf::new();
+        object.push(&tempdir);
+        object.push(format!("code_size_{}.o", opt_level));
+        let status = rustc.command()
+            .args(&[
+                "--crate-type=lib",
+                "--emit=obj",
+                "-C", "codegen-units=1",
+                "-C", &format!("opt-level={}", opt_level),
+                "-o", object.to_str().expect("to_str"),
+                "tests/size/code_size.rs",
+            ])
+            .status()
+            .expect("rustc");
+        assert!(status.success(), "failed to compile tests/size/code_size.rs with -Copt-level={}", opt_level);
+        fs::metadata(&object).expect("object file").len()
+    };
+
+    let size_o3 = object_size("3");
+    let size_os = object_size("s");
+    let size_oz = object_size("z");
+    assert!(size_os < size_o3, "-Copt-level=s ({} bytes) should be smaller than -Copt-level=3 ({} bytes)", size_os, size_o3);
+    assert!(size_oz <= size_os, "-Copt-level=z ({} bytes) should not be bigger than -Copt-level=s ({} bytes)", size_oz, size_os);
+}
+
+/// Check the assembly emitted for the tests in tests/assembly.
+///
+/// The header of these tests can contain these directives:
+///  * `// Compile-flags: <flags>`: the additional flags to pass to rustc.
+///  * `// CHECK: <text>`: the text must be in the assembly.
+///  * `// CHECK-NOT: <text>`: the text must not be in the assembly.
+///
+/// The whitespaces are normalized before comparing.
+fn test_assembly(rustc: &Rustc) {
+    let tempdir = TempDir::new().expect("temp dir");
+    let normalize = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let mut paths: Vec<_> = fs::read_dir("tests/assembly").expect("tests/assembly")
+        .map(|entry| entry.expect("entry").path())
+        .filter(|path| path.extension().expect("extension").to_str().expect("to_str") == "rs")
+        .collect();
+    paths.sort();
+    for path in paths {
+        let source = fs::read_to_string(&path).expect("test source");
+        let header = source.lines()
+            .take_while(|line| line.starts_with("//"))
+            .map(|line| line[2..].trim());
+
+        let mut flags = vec![];
+        let mut checks = vec![];
+        for line in header {
+            if let Some(line_flags) = line.strip_prefix("Compile-flags:") {
+                flags.extend(line_flags.split_whitespace().map(|flag| flag.to_string()));
+            }
+            else if let Some(text) = line.strip_prefix("CHECK:") {
+                checks.push((true, normalize(text)));
+            }
+            else if let Some(text) = line.strip_prefix("CHECK-NOT:") {
+                checks.push((false, normalize(text)));
+            }
+        }
+
+        let mut asm = PathBuf::new();
+        asm.push(&tempdir);
+        asm.push(path.with_extension("s").file_name().expect("file_name"));
+        let status = rustc.command()
+            .args(&[
+                "--crate-type=lib",
+                "--emit=asm",
+                "-C", "codegen-units=1",
+                "-o", asm.to_str().expect("to_str"),
+                path.to_str().expect("to_str"),
+            ])
+            .args(&flags)
+            .status()
+            .expect("rustc");
+        assert!(status.success(), "failed to compile {}", path.display());
+
+        let asm = normalize(&fs::read_to_string(&asm).expect("assembly"));
+        for (should_contain, text) in checks {
+            assert_eq!(asm.contains(&text), should_contain,
+                "{}: the assembly should{} contain `{}`", path.display(), if should_contain { "" } else { " not" }, text);
+        }
+    }
+}
+
+/// Check that -Zcombine-cgu puts the functions of all the codegen units in the object file and
+/// that it is rejected with --emit=asm.
+fn test_combine_cgu(rustc: &Rustc) {
+    let tempdir = TempDir::new().expect("temp dir");
+    let compile = |emit: &str, output: &Path| {
+        rustc.command()
+            .args(&[
+                "-C", "codegen-units=2",
+                "-Zcombine-cgu",
+                "--crate-type=lib",
+                &format!("--emit={}", emit),
+                "-o", output.to_str().expect("to_str"),
+                "tests/combine_cgu/combine_cgu.rs",
+            ])
+            .output()
+            .expect("rustc")
+    };
+
+    let object = tempdir.path().join("combine_cgu.o");
+    let output = compile("obj", &object);
+    assert!(output.status.success(), "failed to compile tests/combine_cgu/combine_cgu.rs: {}", String::from_utf8_lossy(&output.stderr));
+
+    let output = Command::new("nm").arg(&object).output().expect("nm");
+    assert!(output.status.success(), "failed to run nm on the object file");
+    let symbols = String::from_utf8_lossy(&output.stdout);
+    for function in &["root_function", "first_function", "second_function"] {
+        assert!(symbols.contains(function), "{} is missing from the combined object file", function);
+    }
+
+    let output = compile("asm", &tempdir.path().join("combine_cgu.s"));
+    assert!(!output.status.success(), "-Zcombine-cgu should be rejected with --emit=asm");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("-Zcombine-cgu is not supported with --emit=asm"));
+}
+
+/// Check that a crate compiled with this backend can be linked with a crate compiled with the LLVM
+/// backend (and the other way around), which requires both backends to emit the same symbol names.
+fn test_mixed_backends(rustc: &Rustc) {
+    let tempdir = TempDir::new().expect("temp dir");
+    let compile = |use_gcc: bool, args: &[&str]| {
+        let mut command = if use_gcc { rustc.command() } else { rustc.llvm_command() };
+        let status = command
+            .args(args)
+            .status()
+            .expect("rustc");
+        assert!(status.success(), "failed to compile {:?} with the {} backend", args, if use_gcc { "gcc" } else { "LLVM" });
+    };
+
+    for lib_uses_gcc in [false, true] {
+        let mut lib = PathBuf::new();
+        lib.push(&tempdir);
+        lib.push("libmixed_lib.rlib");
+        let mut exe = PathBuf::new();
+        exe.push(&tempdir);
+        exe.push("mixed_backends");
+
+        compile(lib_uses_gcc, &[
+            "--crate-type=lib",
+            "-o", lib.to_str().expect("to_str"),
+            "tests/mixed_backends/mixed_lib.rs",
+        ]);
+        compile(!lib_uses_gcc, &[
+            "--extern", &format!("mixed_lib={}", lib.to_str().expect("to_str")),
+            "-o", exe.to_str().expect("to_str"),
+            "tests/mixed_backends/main.rs",
+        ]);
+
+        let status = Command::new(&exe).status().expect("mixed_backends");
+        assert!(status.success(), "failed to run the program linked with a library compiled by the {} backend", if lib_uses_gcc { "gcc" } else { "LLVM" });
+    }
+}
+
+fn files_with_extension(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    fs::read_dir(dir).expect("read_dir")
+        .map(|entry| entry.expect("entry").path())
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some(extension))
+        .collect()
+}
+
+/// Check that a program compiled with -Cinstrument-coverage writes the gcov data files when it
+/// is run.
+fn test_coverage(rustc: &Rustc) {
+    let tempdir = TempDir::new().expect("temp dir");
+    let exe = tempdir.path().join("profile");
+    let status = rustc.command()
+        .args(&[
+            "-Zno-profiler-runtime",
+            "-C", "instrument-coverage",
+            "-C", "opt-level=2",
+            "-o", exe.to_str().expect("to_str"),
+            "tests/profile/profile.rs",
+        ])
+        .status()
+        .expect("rustc");
+    assert!(status.success(), "failed to compile tests/profile/profile.rs with -Cinstrument-coverage");
+    let notes = files_with_extension(tempdir.path(), "gcno");
+    assert!(!notes.is_empty(), "no .gcno file was written by the compilation");
+    let contains = |data: &[u8], text: &[u8]| data.windows(text.len()).any(|bytes| bytes == text);
+    assert!(notes.iter().any(|path| contains(&fs::read(path).expect("read .gcno"), b"unused_function")), "the unused function is not in the coverage notes");
+
+    let status = Command::new(&exe).status().expect("profile");
+    assert!(status.success(), "failed to run the program compiled with -Cinstrument-coverage");
+    assert!(!files_with_extension(tempdir.path(), "gcda").is_empty(), "no .gcda file was written by the instrumented program");
+}
+
+/// Check that the profile written by a program compiled with -Cprofile-generate can be used
+/// with -Cprofile-use, and that a profile which doesn't match the code is an error.
+fn test_profile_guided_optimization(rustc: &Rustc) {
+    let tempdir = TempDir::new().expect("temp dir");
+    let profile_dir = tempdir.path().join("profile-data");
+    let exe = tempdir.path().join("profile");
+    let compile = |profile_flag: String, source: &str| {
+        rustc.command()
+            .args(&[
+                "-Zno-profiler-runtime",
+                // NOTE: the crate name is the same for both sources so that the profile data of
+                // one is used for the other.
+                "--crate-name", "profile",
+                "-C", "opt-level=2",
+                "-C", &profile_flag,
+                "-o", exe.to_str().expect("to_str"),
+                source,
+            ])
+            .output()
+            .expect("rustc")
+    };
+
+    let output = compile(format!("profile-generate={}", profile_dir.display()), "tests/profile/profile.rs");
+    assert!(output.status.success(), "failed to compile with -Cprofile-generate: {}", String::from_utf8_lossy(&output.stderr));
+    let status = Command::new(&exe).status().expect("profile");
+    assert!(status.success(), "failed to run the program compiled with -Cprofile-generate");
+    assert!(!files_with_extension(&profile_dir, "gcda").is_empty(), "no .gcda file was written in the profile directory");
+
+    let output = compile(format!("profile-use={}", profile_dir.display()), "tests/profile/profile.rs");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "failed to compile with -Cprofile-use: {}", stderr);
+    assert!(!stderr.contains("does not exist"), "the profile data was not found: {}", stderr);
+    let status = Command::new(&exe).status().expect("profile");
+    assert!(status.success(), "failed to run the program compiled with -Cprofile-use");
+
+    let output = compile(format!("profile-use={}", profile_dir.display()), "tests/profile/profile_mismatch.rs");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success(), "a profile that doesn't match the code should be an error");
+    assert!(stderr.contains("profile data"), "unexpected error for a profile that doesn't match the code: {}", stderr);
+}
+
+/// Check that the local variables, including the slices, the enums and the ones captured by
+/// reference by a closure, can be printed in gdb.
+fn test_debuginfo(rustc: &Rustc) {
+    if Command::new("gdb").arg("--version").output().is_err() {
+        println!("Skipping the debuginfo test: gdb is not installed");
+        return;
+    }
+
+    let tempdir = TempDir::new().expect("temp dir");
+    let exe = tempdir.path().join("debuginfo");
+    let status = rustc.command()
+        .args(&[
+            "-C", "debuginfo=2",
+            "-o", exe.to_str().expect("to_str"),
+            "tests/debuginfo/debuginfo.rs",
+        ])
+        .status()
+        .expect("rustc");
+    assert!(status.success(), "failed to compile tests/debuginfo/debuginfo.rs");
+
+    // NOTE: the variables captured by reference by the closure are pointers to the Rust variables
+    // (see dbg_var_addr()), hence the dereferences.
+    let output = Command::new("gdb")
+        .args(&[
+            "-batch",
+            "-ex", "break debuginfo.rs:7",
+            "-ex", "run",
+            // In main.
+            "-ex", "up",
+            "-ex", "print number",
+            "-ex", "print point",
+            "-ex", "print slice",
+            "-ex", "print shape",
+            "-ex", "continue",
+            // In the closure.
+            "-ex", "up",
+            "-ex", "print *captured",
+            "-ex", "print *number",
+            exe.to_str().expect("to_str"),
+        ])
+        .output()
+        .expect("gdb");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "gdb failed: {}", String::from_utf8_lossy(&output.stderr));
+    // NOTE: only the tag of the enums is described (see field_name()).
+    for expected in &["$1 = 42", "x = 1", "y = 2", "length = 3", "tag = 1", "$5 = 7", "$6 = 42"] {
+        assert!(stdout.contains(expected), "gdb output should contain `{}`:\n{}", expected, stdout);
+    }
+}
+
+/// Check that -Csplit-debuginfo=unpacked moves the debug info to .dwo files which are referenced
+/// by the executable.
+fn test_split_debuginfo(rustc: &Rustc) {
+    let tempdir = TempDir::new().expect("temp dir");
+    let exe = tempdir.path().join("debuginfo");
+    let output = rustc.command()
+        .args(&[
+            "-Zunstable-options",
+            "-C", "debuginfo=2",
+            "-C", "split-debuginfo=unpacked",
+            "-o", exe.to_str().expect("to_str"),
+            "tests/debuginfo/debuginfo.rs",
+        ])
+        .output()
+        .expect("rustc");
+    assert!(output.status.success(), "failed to compile tests/debuginfo/debuginfo.rs with -Csplit-debuginfo=unpacked: {}", String::from_utf8_lossy(&output.stderr));
+
+    let dwo_files = files_with_extension(tempdir.path(), "dwo");
+    assert!(!dwo_files.is_empty(), "no .dwo file was written by the compilation");
+    let executable = fs::read(&exe).expect("executable");
+    let contains = |data: &[u8], text: &[u8]| data.windows(text.len()).any(|bytes| bytes == text);
+    for dwo_file in dwo_files {
+        let dwo = fs::read(&dwo_file).expect("dwo file");
+        assert!(contains(&dwo, b".debug_info.dwo"), "{} doesn't contain the debug info", dwo_file.display());
+        let name = dwo_file.to_str().expect("to_str");
+        assert!(contains(&executable, name.as_bytes()), "{} is not referenced by the executable", name);
+    }
+}
+
+/// Check that the programs compiled with fat and thin LTO can call the code of the upstream
+/// crates, including when rustc serializes the modules before LTO because of the incremental
+/// compilation.
+fn test_lto(rustc: &Rustc) {
+    let tempdir = TempDir::new().expect("temp dir");
+    let incremental_dir = tempdir.path().join("incremental");
+    let thin_incremental_dir = tempdir.path().join("thin-incremental");
+    let compile = |args: &[&str]| {
+        rustc.command()
+            .args(&[
+                "-C", "opt-level=2",
+                "--out-dir", tempdir.path().to_str().expect("to_str"),
+                "-L", tempdir.path().to_str().expect("to_str"),
+            ])
+            .args(args)
+            .output()
+            .expect("rustc")
+    };
+
+    let output = compile(&["--crate-type=rlib", "tests/lto/lto_lib.rs"]);
+    assert!(output.status.success(), "failed to compile tests/lto/lto_lib.rs: {}", String::from_utf8_lossy(&output.stderr));
+
+    let incremental_flag = format!("incremental={}", incremental_dir.display());
+    let thin_incremental_flag = format!("incremental={}", thin_incremental_dir.display());
+    let lto_flags: &[&[&str]] = &[
+        &["-C", "lto=fat", "-C", "codegen-units=4"],
+        &["-C", "lto=fat", "-C", "codegen-units=4", "-C", &incremental_flag],
+        &["-C", "lto=thin", "-C", "codegen-units=4"],
+        &["-C", "lto=thin", "-C", "codegen-units=4", "-C", &incremental_flag],
+        // NOTE: without -Clto, the codegen units of the crate go through thin local LTO, which
+        // compiles them from memory, or from GIMPLE bytecode in incremental builds. Compile a
+        // second time to reuse the objects of the previous compilation.
+        &["-C", "codegen-units=4"],
+        &["-C", "codegen-units=4", "-C", &thin_incremental_flag],
+        &["-C", "codegen-units=4", "-C", &thin_incremental_flag],
+    ];
+    for flags in lto_flags {
+        let mut args = flags.to_vec();
+        args.push("tests/lto/lto_bin.rs");
+        let output = compile(&args);
+        assert!(output.status.success(), "failed to compile tests/lto/lto_bin.rs with {:?}: {}", flags, String::from_utf8_lossy(&output.stderr));
+        let output = Command::new(tempdir.path().join("lto_bin")).output().expect("lto_bin");
+        assert!(output.status.success(), "failed to run the program compiled with {:?}", flags);
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "Hello, LTO!\n10\n", "wrong output of the program compiled with {:?}", flags);
+    }
+}
+
+/// Check that the weak symbols defined in two object files don't give multiple definitions
+/// errors and that a weak declaration of an undefined symbol is null.
+fn test_weak_linkage(rustc: &Rustc) {
+    let tempdir = TempDir::new().expect("temp dir");
+    let compile = |args: &[&str]| {
+        rustc.command()
+            .args(&[
+                "-C", "codegen-units=1",
+                "--out-dir", tempdir.path().to_str().expect("to_str"),
+                "-L", tempdir.path().to_str().expect("to_str"),
+            ])
+            .args(args)
+            .output()
+            .expect("rustc")
+    };
+
+    let output = compile(&["--crate-type=rlib", "tests/weak_linkage/weak_lib.rs"]);
+    assert!(output.status.success(), "failed to compile tests/weak_linkage/weak_lib.rs: {}", String::from_utf8_lossy(&output.stderr));
+    let output = compile(&["tests/weak_linkage/weak_bin.rs"]);
+    assert!(output.status.success(), "failed to compile tests/weak_linkage/weak_bin.rs: {}", String::from_utf8_lossy(&output.stderr));
+
+    let output = Command::new(tempdir.path().join("weak_bin")).output().expect("weak_bin");
+    assert!(output.status.success(), "failed to run tests/weak_linkage/weak_bin.rs");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "2\n3\n4\ntrue\n5\n");
+}
+
+/// Check that the #[used] statics are not removed by the linker garbage collection of the sections.
+fn test_used(rustc: &Rustc) {
+    let tempdir = TempDir::new().expect("temp dir");
+    let exe = tempdir.path().join("used");
+    let output = rustc.command()
+        .args(&[
+            "-C", "opt-level=3",
+            "-C", "link-arg=-Wl,--gc-sections",
+            "-o", exe.to_str().expect("to_str"),
+            "tests/used/used.rs",
+        ])
+        .output()
+        .expect("rustc");
+    assert!(output.status.success(), "failed to compile tests/used/used.rs: {}", String::from_utf8_lossy(&output.stderr));
+
+    let output = Command::new("nm").arg(&exe).output().expect("nm");
+    assert!(output.status.success(), "failed to run nm on the executable");
+    let symbols = String::from_utf8_lossy(&output.stdout);
+    assert!(symbols.contains("13USED_REGISTRY"), "the #[used] static was removed by the linker");
+    assert!(!symbols.contains("15UNUSED_REGISTRY"), "the unused static was not removed");
+}
+
+/// Check that -Clinker-plugin-lto optimizes the Rust code together with C code compiled with -flto.
+fn test_linker_plugin_lto(rustc: &Rustc) {
+    let tempdir = TempDir::new().expect("temp dir");
+    let c_object = tempdir.path().join("c_functions.o");
+    let output = Command::new("gcc")
+        .args(&["-O2", "-flto", "-c", "tests/linker_plugin_lto/c_functions.c", "-o", c_object.to_str().expect("to_str")])
+        .output()
+        .expect("gcc");
+    assert!(output.status.success(), "failed to compile tests/linker_plugin_lto/c_functions.c: {}", String::from_utf8_lossy(&output.stderr));
+
+    let exe = tempdir.path().join("linker_plugin_lto");
+    let compile = |linker_args: &[&str]| {
+        rustc.command()
+            .args(&[
+                "-C", "opt-level=2",
+                "-C", "linker-plugin-lto",
+                "-C", &format!("link-arg={}", c_object.display()),
+                "-o", exe.to_str().expect("to_str"),
+                "tests/linker_plugin_lto/main.rs",
+            ])
+            .args(linker_args)
+            .output()
+            .expect("rustc")
+    };
+
+    let output = compile(&[]);
+    assert!(!output.status.success(), "-Clinker-plugin-lto should require the linker wrapper");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("-Clinker-plugin-lto requires -Clinker=path/to/linker_plugin_lto.sh"));
+
+    let output = compile(&["-C", &format!("linker={}/linker_plugin_lto.sh", rustc.current_dir), "-C", "target-cpu=native"]);
+    assert!(output.status.success(), "failed to compile tests/linker_plugin_lto/main.rs: {}", String::from_utf8_lossy(&output.stderr));
+
+    let output = Command::new(&exe).output().expect("linker_plugin_lto");
+    assert!(output.status.success(), "failed to run tests/linker_plugin_lto/main.rs");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "5\n42\n");
+
+    // NOTE: the C functions are only used by the Rust code, so they are removed after being
+    // inlined into it.
+    let exe_content = fs::read(&exe).expect("read executable");
+    let contains = |data: &[u8], text: &[u8]| data.windows(text.len()).any(|bytes| bytes == text);
+    assert!(!contains(&exe_content, b"c_add\0"), "c_add() was not inlined into the Rust code");
+}
+
+/// Check that the panics can be caught and that the values are dropped during unwinding.
+///
+/// NOTE: this requires a sysroot compiled with -Cpanic=unwind, so this only runs when
+/// CG_GCCJIT_PANIC_STRATEGY is unwind.
+fn test_unwind(rustc: &Rustc) {
+    if rustc.panic_strategy != "unwind" {
+        return;
+    }
+
+    let tempdir = TempDir::new().expect("temp dir");
+    let exe = tempdir.path().join("catch_unwind");
+    for opt_level in &["0", "3"] {
+        let output = rustc.command()
+            .args(&[
+                "-C", &format!("opt-level={}", opt_level),
+                "-o", exe.to_str().expect("to_str"),
+                "tests/unwind/catch_unwind.rs",
+            ])
+            .output()
+            .expect("rustc");
+        assert!(output.status.success(), "failed to compile tests/unwind/catch_unwind.rs with -Copt-level={}: {}", opt_level, String::from_utf8_lossy(&output.stderr));
+
+        let output = Command::new(&exe).output().expect("catch_unwind");
+        assert!(output.status.success(), "the program compiled with -Copt-level={} failed: {}", opt_level, String::from_utf8_lossy(&output.stderr));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "caught: value too big: 42\ndrops: 4\n", "wrong output of the program compiled with -Copt-level={}", opt_level);
+    }
+}
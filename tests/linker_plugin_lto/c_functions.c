@@ -0,0 +1,9 @@
+unsigned int rust_double(unsigned int value);
+
+unsigned int c_add(unsigned int a, unsigned int b) {
+    return a + b;
+}
+
+unsigned int c_call_rust(unsigned int value) {
+    return rust_double(value);
+}
@@ -0,0 +1,16 @@
+extern "C" {
+    fn c_add(a: u32, b: u32) -> u32;
+    fn c_call_rust(value: u32) -> u32;
+}
+
+#[no_mangle]
+pub extern "C" fn rust_double(value: u32) -> u32 {
+    value * 2
+}
+
+fn main() {
+    unsafe {
+        println!("{}", c_add(2, 3));
+        println!("{}", c_call_rust(21));
+    }
+}
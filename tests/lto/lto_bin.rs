@@ -0,0 +1,8 @@
+extern crate lto_lib;
+
+fn main() {
+    // NOTE: these functions are defined in the object files of the upstream crates, which are not
+    // linked by rustc when doing LTO.
+    println!("{}", lto_lib::greeting("LTO"));
+    println!("{}", lto_lib::sum(&[1, 2, 3, 4]));
+}
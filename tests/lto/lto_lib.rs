@@ -0,0 +1,7 @@
+pub fn sum(values: &[u32]) -> u32 {
+    values.iter().sum()
+}
+
+pub fn greeting(name: &str) -> String {
+    format!("Hello, {}!", name)
+}
@@ -0,0 +1,12 @@
+extern crate mixed_lib;
+
+use mixed_lib::{Counter, Double, TOTAL, add_to_total};
+
+fn main() {
+    let mut counter = Counter { count: 0 };
+    assert_eq!(counter.increment(), 1);
+    assert_eq!(counter.increment(), 2);
+    assert_eq!(counter.double(), 4);
+    add_to_total(&counter);
+    assert_eq!(unsafe { TOTAL }, 4);
+}
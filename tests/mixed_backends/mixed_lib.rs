@@ -0,0 +1,33 @@
+// The legacy symbol names of these functions contain `$` and `.`.
+
+pub struct Counter {
+    pub count: u32,
+}
+
+impl Counter {
+    #[inline(never)]
+    pub fn increment(&mut self) -> u32 {
+        self.count += 1;
+        self.count
+    }
+}
+
+pub trait Double {
+    fn double(&self) -> u32;
+}
+
+impl Double for Counter {
+    #[inline(never)]
+    fn double(&self) -> u32 {
+        self.count * 2
+    }
+}
+
+pub static mut TOTAL: u32 = 0;
+
+#[inline(never)]
+pub fn add_to_total(counter: &Counter) {
+    unsafe {
+        TOTAL += counter.double();
+    }
+}
@@ -0,0 +1,26 @@
+use std::env;
+
+fn classify(value: usize) -> &'static str {
+    if value % 3 == 0 {
+        "fizz"
+    }
+    else if value % 5 == 0 {
+        "buzz"
+    }
+    else {
+        "number"
+    }
+}
+
+// NOTE: this function is used to check that the functions which are never called are part of the
+// coverage report.
+#[allow(dead_code)]
+fn unused_function() -> usize {
+    42
+}
+
+fn main() {
+    let count = env::args().count() * 100;
+    let fizz = (0..count).filter(|&value| classify(value) == "fizz").count();
+    assert_eq!(fizz, 34);
+}
@@ -0,0 +1,25 @@
+// Same as profile.rs, but the control flow of classify() is different, so the profile data of
+// profile.rs doesn't match this code.
+
+use std::env;
+
+fn classify(value: usize) -> &'static str {
+    if value % 15 == 0 {
+        "fizzbuzz"
+    }
+    else if value % 3 == 0 {
+        "fizz"
+    }
+    else if value % 5 == 0 {
+        "buzz"
+    }
+    else {
+        "number"
+    }
+}
+
+fn main() {
+    let count = env::args().count() * 100;
+    let fizz = (0..count).filter(|&value| classify(value) == "fizz").count();
+    assert_eq!(fizz, 27);
+}
@@ -0,0 +1,60 @@
+// Compiler:
+//
+// Run-time:
+//   status: 0
+
+#![feature(auto_traits, lang_items, no_core, start)]
+
+#![no_std]
+#![no_core]
+
+/*
+ * Core
+ */
+
+// Because we don't have core yet.
+#[lang = "sized"]
+pub trait Sized {}
+
+#[lang = "copy"]
+trait Copy {
+}
+
+impl Copy for isize {}
+
+#[lang = "receiver"]
+trait Receiver {
+}
+
+#[lang = "freeze"]
+pub(crate) unsafe auto trait Freeze {}
+
+/*
+ * Code
+ */
+
+static mut STATUS: isize = 1;
+
+#[link_section = ".text.custom_section"]
+#[no_mangle]
+pub fn function_in_custom_section() -> isize {
+    0
+}
+
+extern "C" fn initialize() {
+    unsafe {
+        STATUS = function_in_custom_section();
+    }
+}
+
+// The functions in .init_array are called before main, like the std does to initialize argc/argv.
+#[used]
+#[link_section = ".init_array.00099"]
+static mut INIT_ARRAY: extern "C" fn() = initialize;
+
+#[start]
+fn main(_argc: isize, _argv: *const *const u8) -> isize {
+    unsafe {
+        STATUS
+    }
+}
@@ -0,0 +1,48 @@
+// Compiler:
+//
+// Run-time:
+//   status: 0
+
+#![feature(auto_traits, lang_items, no_core, start)]
+
+#![no_std]
+#![no_core]
+
+/*
+ * Core
+ */
+
+// Because we don't have core yet.
+#[lang = "sized"]
+pub trait Sized {}
+
+#[lang = "copy"]
+trait Copy {
+}
+
+impl Copy for isize {}
+
+#[lang = "receiver"]
+trait Receiver {
+}
+
+#[lang = "freeze"]
+pub(crate) unsafe auto trait Freeze {}
+
+/*
+ * Code
+ */
+
+// SSE2 is part of the baseline of x86-64, so this function can always be called.
+// NOTE: the assembly test target_feature_attribute.rs checks that the attribute is applied.
+#[target_feature(enable = "sse2,sse3")]
+unsafe fn with_target_features(value: isize) -> isize {
+    value
+}
+
+#[start]
+fn main(_argc: isize, _argv: *const *const u8) -> isize {
+    unsafe {
+        with_target_features(0)
+    }
+}
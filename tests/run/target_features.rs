@@ -0,0 +1,50 @@
+// Compiler:
+//
+// Run-time:
+//   status: 0
+
+#![feature(auto_traits, lang_items, no_core, start)]
+
+#![no_std]
+#![no_core]
+
+/*
+ * Core
+ */
+
+// Because we don't have core yet.
+#[lang = "sized"]
+pub trait Sized {}
+
+#[lang = "copy"]
+trait Copy {
+}
+
+impl Copy for isize {}
+
+#[lang = "receiver"]
+trait Receiver {
+}
+
+#[lang = "freeze"]
+pub(crate) unsafe auto trait Freeze {}
+
+/*
+ * Code
+ */
+
+// SSE2 is part of the baseline of x86-64.
+#[cfg(target_feature = "sse2")]
+fn sse2_status() -> isize {
+    0
+}
+
+#[cfg(not(target_feature = "sse2"))]
+fn sse2_status() -> isize {
+    1
+}
+
+#[start]
+fn main(_argc: isize, _argv: *const *const u8) -> isize {
+    sse2_status()
+}
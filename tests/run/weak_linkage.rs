@@ -0,0 +1,53 @@
+// Compiler:
+//
+// Run-time:
+//   status: 0
+
+#![feature(auto_traits, lang_items, linkage, no_core, start)]
+
+#![no_std]
+#![no_core]
+
+/*
+ * Core
+ */
+
+// Because we don't have core yet.
+#[lang = "sized"]
+pub trait Sized {}
+
+#[lang = "copy"]
+trait Copy {
+}
+
+impl Copy for isize {}
+
+#[lang = "receiver"]
+trait Receiver {
+}
+
+#[lang = "freeze"]
+pub(crate) unsafe auto trait Freeze {}
+
+/*
+ * Code
+ */
+
+// The weak symbols can be defined in multiple object files without a linker error.
+#[linkage = "weak"]
+#[no_mangle]
+pub fn weak_function() -> isize {
+    0
+}
+
+#[linkage = "linkonce_odr"]
+#[no_mangle]
+pub fn linkonce_odr_function() -> isize {
+    0
+}
+
+#[start]
+fn main(_argc: isize, _argv: *const *const u8) -> isize {
+    linkonce_odr_function();
+    weak_function()
+}
@@ -0,0 +1,35 @@
+// Code that gets unrolled, vectorized and inlined at -O3, but not when optimizing for size.
+
+#![no_std]
+
+#[inline]
+fn mix(value: u32, key: u32) -> u32 {
+    let value = value.wrapping_mul(0x9E37_79B9) ^ key;
+    value.rotate_left(13).wrapping_add(value >> 7)
+}
+
+#[no_mangle]
+pub fn sum_squares(values: &[u32]) -> u32 {
+    let mut sum = 0u32;
+    for &value in values {
+        sum = sum.wrapping_add(value.wrapping_mul(value));
+    }
+    sum
+}
+
+#[no_mangle]
+pub fn hash(values: &[u32]) -> u32 {
+    let mut hash = 0;
+    for (index, &value) in values.iter().enumerate() {
+        hash = mix(hash, value);
+        hash = mix(hash, index as u32);
+    }
+    hash
+}
+
+#[no_mangle]
+pub fn scale(values: &mut [f32], factor: f32) {
+    for value in values {
+        *value *= factor;
+    }
+}
@@ -0,0 +1,43 @@
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+struct Guard;
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        DROPS.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[inline(never)]
+fn double(value: u32) -> u32 {
+    let _guard = Guard;
+    if value > 10 {
+        panic!("value too big: {}", value);
+    }
+    value * 2
+}
+
+fn main() {
+    // Don't print the messages of the expected panics.
+    panic::set_hook(Box::new(|_| {}));
+
+    assert_eq!(panic::catch_unwind(|| double(2)).ok(), Some(4));
+
+    let error = panic::catch_unwind(|| double(42)).expect_err("panic");
+    let message = error.downcast::<String>().expect("panic message");
+    println!("caught: {}", message);
+
+    // The panic goes through a cleanup and a catch before being resumed.
+    let result = panic::catch_unwind(|| {
+        let _guard = Guard;
+        let error = panic::catch_unwind(|| double(11)).expect_err("panic");
+        panic::resume_unwind(error);
+    });
+    assert!(result.is_err());
+
+    // The guards are dropped when returning and during unwinding.
+    println!("drops: {}", DROPS.load(Ordering::SeqCst));
+}
@@ -0,0 +1,9 @@
+// The #[used] statics must be kept even if they are not referenced, including by the linker.
+#[used]
+static USED_REGISTRY: [u32; 3] = [1, 2, 3];
+
+#[allow(dead_code)]
+static UNUSED_REGISTRY: [u32; 3] = [4, 5, 6];
+
+fn main() {
+}
@@ -0,0 +1,38 @@
+#![feature(linkage)]
+
+extern crate weak_lib;
+
+#[no_mangle]
+pub fn overridden_function() -> u32 {
+    2
+}
+
+#[linkage = "weak"]
+#[no_mangle]
+pub fn weak_function() -> u32 {
+    3
+}
+
+#[linkage = "linkonce_odr"]
+#[no_mangle]
+pub fn linkonce_odr_function() -> u32 {
+    4
+}
+
+extern "C" {
+    // The weak declarations are null when the symbol is not defined.
+    #[linkage = "extern_weak"]
+    static UNDEFINED_STATIC: *const u32;
+    #[linkage = "extern_weak"]
+    static DEFINED_STATIC: *const u32;
+}
+
+fn main() {
+    println!("{}", weak_lib::call_overridden_function());
+    println!("{}", weak_function());
+    println!("{}", linkonce_odr_function());
+    unsafe {
+        println!("{}", UNDEFINED_STATIC.is_null());
+        println!("{}", *DEFINED_STATIC);
+    }
+}
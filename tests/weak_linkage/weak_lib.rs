@@ -0,0 +1,28 @@
+#![feature(linkage)]
+
+// Overridden by the strong definition in the executable.
+#[linkage = "weak"]
+#[no_mangle]
+pub fn overridden_function() -> u32 {
+    1
+}
+
+// Also defined in the executable: the linker keeps only one of the definitions.
+#[linkage = "weak"]
+#[no_mangle]
+pub fn weak_function() -> u32 {
+    3
+}
+
+#[linkage = "linkonce_odr"]
+#[no_mangle]
+pub fn linkonce_odr_function() -> u32 {
+    4
+}
+
+#[no_mangle]
+pub static DEFINED_STATIC: u32 = 5;
+
+pub fn call_overridden_function() -> u32 {
+    overridden_function()
+}